@@ -0,0 +1,194 @@
+//! Repeated timing of registered days with basic statistics.
+//!
+use std::fmt;
+use std::time::Duration;
+
+use crate::json::{Object, ToJson};
+use crate::runner::{self, format_duration};
+use crate::solution::{Answer, Day, Part};
+
+/// How many times each part is run.
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    pub warmup: usize,
+    pub iterations: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config { warmup: 1, iterations: 10 }
+    }
+}
+
+/// Summary of measured run times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    pub samples: usize,
+    pub min: Duration,
+    pub median: Duration,
+    pub mean: Duration,
+    pub stddev: Duration,
+}
+
+impl Stats {
+    /// Computes statistics of the samples, `None` if there are no samples.
+    pub fn new(samples: &[Duration]) -> Option<Stats> {
+        if samples.is_empty() {
+            return None
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let n = sorted.len();
+        let median = if n % 2 == 1 {
+            sorted[n / 2]
+        } else {
+            (sorted[n / 2 - 1] + sorted[n / 2]) / 2
+        };
+        let nanos: Vec<f64> = sorted.iter().map(|d| d.as_nanos() as f64).collect();
+        let mean = nanos.iter().sum::<f64>() / n as f64;
+        let variance = if n > 1 {
+            nanos.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64
+        } else {
+            0.0
+        };
+        Some(Stats {
+            samples: n,
+            min: sorted[0],
+            median,
+            mean: Duration::from_nanos(mean.round() as u64),
+            stddev: Duration::from_nanos(variance.sqrt().round() as u64),
+        })
+    }
+}
+
+/// Benchmark result of a single part.
+#[derive(Debug)]
+pub struct Bench {
+    pub day: usize,
+    pub part: Part,
+    pub result: anyhow::Result<(Answer, Stats)>,
+}
+
+/// Runs the part `config.warmup` times unmeasured, then `config.iterations` times measured.
+/// Stops at the first failed run.
+pub fn bench(day: &Day, data: &str, part: Part, config: &Config) -> Bench {
+    let mut samples = Vec::with_capacity(config.iterations);
+    let mut last = None;
+    for i in 0..config.warmup + config.iterations {
+        let outcome = runner::run(day, data, part);
+        match outcome.answer {
+            Ok(answer) => last = Some(answer),
+            Err(e) => return Bench { day: day.number, part, result: Err(e) },
+        }
+        if i >= config.warmup {
+            samples.push(outcome.elapsed);
+        }
+    }
+    let result = last
+        .zip(Stats::new(&samples))
+        .ok_or_else(|| anyhow::anyhow!("no samples collected"));
+    Bench { day: day.number, part, result }
+}
+
+/// Benchmark results as a plain-text table.
+pub struct Table<'a>(pub &'a [Bench]);
+
+impl<'a> fmt::Display for Table<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:>3}  {:>4}  {:>12}  {:>12}  {:>12}  {:>12}  {:>4}",
+            "Day", "Part", "Min", "Median", "Mean", "Stddev", "Runs")?;
+        writeln!(f, "{}", "-".repeat(73))?;
+        for bench in self.0 {
+            match &bench.result {
+                Ok((_, stats)) => writeln!(f, "{:>3}  {:>4}  {:>12}  {:>12}  {:>12}  {:>12}  {:>4}",
+                    bench.day,
+                    bench.part.number(),
+                    format_duration(stats.min),
+                    format_duration(stats.median),
+                    format_duration(stats.mean),
+                    format_duration(stats.stddev),
+                    stats.samples,
+                )?,
+                Err(e) => writeln!(f, "{:>3}  {:>4}  error: {:#}", bench.day, bench.part.number(), e)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ToJson for Bench {
+    fn write_json(&self, out: &mut String) {
+        let obj = Object::new(out)
+            .field("day", &self.day)
+            .field("part", &self.part.number());
+        match &self.result {
+            Ok((answer, stats)) => obj
+                .field("answer", &answer.value)
+                .field("samples", &stats.samples)
+                .field("min_ns", &(stats.min.as_nanos() as u64))
+                .field("median_ns", &(stats.median.as_nanos() as u64))
+                .field("mean_ns", &(stats.mean.as_nanos() as u64))
+                .field("stddev_ns", &(stats.stddev.as_nanos() as u64))
+                .finish(),
+            Err(e) => obj.field("error", &format!("{:#}", e)).finish(),
+        }
+    }
+}
+
+/// Full benchmark report: configuration and per-part results.
+pub struct Report<'a> {
+    pub config: Config,
+    pub results: &'a [Bench],
+}
+
+impl<'a> ToJson for Report<'a> {
+    fn write_json(&self, out: &mut String) {
+        Object::new(out)
+            .field("warmup", &self.config.warmup)
+            .field("iterations", &self.config.iterations)
+            .field("results", self.results)
+            .finish();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{bench, Config, Stats};
+    use crate::json::ToJson;
+    use crate::solution::Part;
+    use std::time::Duration;
+
+    #[test]
+    fn stats() {
+        let ms = Duration::from_millis;
+        let stats = Stats::new(&[ms(4), ms(1), ms(3), ms(2)]).expect("non-empty samples");
+        assert_eq!(stats.samples, 4);
+        assert_eq!(stats.min, ms(1));
+        assert_eq!(stats.median, Duration::from_micros(2500));
+        assert_eq!(stats.mean, Duration::from_micros(2500));
+        assert_eq!(stats.stddev, Duration::from_nanos(1_290_994));
+
+        let stats = Stats::new(&[ms(7)]).expect("non-empty samples");
+        assert_eq!(stats.median, ms(7));
+        assert_eq!(stats.stddev, Duration::ZERO);
+        assert!(Stats::new(&[]).is_none());
+    }
+
+    #[test]
+    fn runs() {
+        let day = crate::day(1).expect("day 1 is registered");
+        let config = Config { warmup: 2, iterations: 3 };
+        let result = bench(day, "1\n\n2\n", Part::One, &config);
+        let (answer, stats) = result.result.as_ref().expect("valid input");
+        assert_eq!(answer.value, "2");
+        assert_eq!(stats.samples, 3);
+        assert!(result.to_json().starts_with(r#"{"day":1,"part":1,"answer":"2","samples":3,"#));
+
+        let none = bench(day, "1\n\n2\n", Part::One, &Config { warmup: 1, iterations: 0 });
+        assert!(none.result.is_err());
+
+        let failed = bench(day, "x", Part::One, &config);
+        assert!(failed.result.is_err());
+        assert!(failed.to_json().contains(r#""error":"#));
+    }
+}
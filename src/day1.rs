@@ -1,26 +1,57 @@
-pub fn main(data: &str) -> anyhow::Result<(usize, usize)> {
+use anyhow::anyhow as err;
+
+use crate::parser;
+use crate::solution::{Answer, Solution};
+
+/// Parses calories carried by each elf, sorted in descending order.
+pub fn parse(data: &str) -> anyhow::Result<Vec<usize>> {
     let mut cargo = Vec::new();
     let mut calories = 0usize;
-    for line in data.lines() {
+    for (idx, line) in data.lines().enumerate() {
         if line.is_empty() {
             cargo.push(calories);
             calories = 0usize;
             continue;
         }
-        calories += line.parse::<usize>()?;
+        let n = parser::number::<usize>(line, line).map_err(|e| e.line(idx + 1))?;
+        calories = calories.checked_add(n).ok_or_else(|| err!("calories overflow at line {}", idx + 1))?;
     }
     cargo.push(calories);
 
-    cargo.sort_by_key(|c| -(*c as isize));
+    cargo.sort_by(|a, b| b.cmp(a));
+    Ok(cargo)
+}
+
+pub fn part1(cargo: &[usize]) -> anyhow::Result<usize> {
+    cargo.first().copied().ok_or_else(|| err!("no elves"))
+}
 
-    let max_calories = cargo[0];
-    let top_three = cargo.iter().take(3).sum();
-    Ok((max_calories, top_three))
+pub fn part2(cargo: &[usize]) -> anyhow::Result<usize> {
+    cargo.iter()
+        .take(3)
+        .try_fold(0usize, |acc, &c| acc.checked_add(c))
+        .ok_or_else(|| err!("calories overflow"))
+}
+
+pub struct Solver;
+
+impl Solution for Solver {
+    type Input = Vec<usize>;
+
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Max calories", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Top 3 sum", part2(input)?))
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::main;
+    use super::{parse, part1, part2};
 
     static DATA: &str = concat!(
         "1000\n", "2000\n", "3000\n", "\n", "4000\n", "\n", "5000\n", "6000\n", "\n", "7000\n",
@@ -29,8 +60,13 @@ mod test {
 
     #[test]
     fn solution() {
-        let (a, b) = main(DATA).expect("invalid input");
-        assert_eq!(a, 24000);
-        assert_eq!(b, 45000);
+        let cargo = parse(DATA).expect("invalid input");
+        assert_eq!(part1(&cargo).expect("invalid input"), 24000);
+        assert_eq!(part2(&cargo).expect("invalid input"), 45000);
+    }
+
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
     }
 }
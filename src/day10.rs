@@ -1,52 +1,80 @@
-pub fn main(data: &str) -> anyhow::Result<(i64, String)> {
-    let part1 = data.lines()
-        .into_elves_cpu_cmd_iterator()
+use std::str;
+
+use crate::parser::{self, ParseError};
+use crate::solution::{Answer, Solution};
+
+pub fn parse(data: &str) -> anyhow::Result<Vec<Instruction>> {
+    Ok(parser::lines(data, str::parse)?)
+}
+
+pub fn part1(program: &[Instruction]) -> anyhow::Result<i64> {
+    Ok(program.iter()
+        .elves_cpu_cmd_iterator()
         .enumerate()
         .map(|(i, x)| (i+1, x))
         .skip(19)
         .step_by(40)
         .map(|(tick, x)| (tick as i64) * x)
-        .sum();
+        .sum())
+}
 
-    let mut prev_x = 1;
-    let mut sprite = 7u64; // initialy sprite is vissible;
-    let mask: u64 = 0b1111_1111_1111_1111_1111_1111_1111_1111_1111_1111;
+pub fn part2(program: &[Instruction]) -> anyhow::Result<String> {
     let mut screen = ['.'; 6*41];
     for i in 0..6 {
         screen[i * 41 + 40] = '\n';
     }
-    for (idx, x) in data.lines().into_elves_cpu_cmd_iterator().enumerate() {
-        if prev_x != x {
-            if x > 0 {
-                sprite = (7u64 << x) >> 1;
-            } else {
-                sprite = (7u64 >> x.abs()) >> 1;
-            }
-            prev_x = x;
-        }
-        let pixel = mask & sprite;
-        // println!("CRT: {} {} {} {}", row, col, x, is_lit);
-
+    for (idx, x) in program.iter().elves_cpu_cmd_iterator().take(6 * 40).enumerate() {
+        // sprite is 3 pixels wide, centered at x
         let (row, col) = (idx / 40, idx % 40);
-        let is_lit = pixel & (1 << col) > 0;
+        let is_lit = (x - col as i64).abs() <= 1;
         if is_lit {
             screen[row * 41 + col] = '#';
         }
     }
 
-    let part2 = screen.iter().collect::<String>();
-    println!("---------");
-    print!("{}", part2);
-    println!("---------");
+    Ok(screen.iter().collect::<String>())
+}
+
+pub struct Solver;
 
-    Ok((part1, part2))
+impl Solution for Solver {
+    type Input = Vec<Instruction>;
+
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Signal strengths sum", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("CRT image", part2(input)?))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Instruction {
+    Noop,
+    Addx(i32),
+}
+
+impl str::FromStr for Instruction {
+    type Err = ParseError;
+    fn from_str(val: &str) -> Result<Self, ParseError> {
+        if let Some(x) = val.strip_prefix("addx ") {
+            Ok(Instruction::Addx(parser::number(val, x)?))
+        } else if val == "noop" {
+            Ok(Instruction::Noop)
+        } else {
+            Err(ParseError::at(val, val, "expected 'addx' or 'noop'"))
+        }
+    }
 }
 
 struct ElvesCpuCommands<'a, I>
 where
-    I: Iterator<Item=&'a str>,
+    I: Iterator<Item=&'a Instruction>,
 {
-    lines: &'a mut I,
+    instructions: &'a mut I,
     reg_x: i64,
     next_cmd_ticks: Option<u8>,
     next_cmd_value: Option<i64>,
@@ -54,21 +82,19 @@ where
 
 impl<'a, I> ElvesCpuCommands<'a, I>
 where
-    I: Iterator<Item=&'a str>,
+    I: Iterator<Item=&'a Instruction>,
 {
     fn maybe_pull_command(&mut self) -> Option<()> {
         if self.next_cmd_ticks.is_none() { 
-            let cmd = self.lines.next()?;
-            // println!("line: {}", cmd);
-            if cmd.starts_with("addx ") {
-                let next_val: i64 = cmd[5..].parse().expect("invalid input");
-                self.next_cmd_value.replace(next_val);
-                self.next_cmd_ticks.replace(2);
-            } else if cmd == "noop" {
-                self.next_cmd_value.take();
-                self.next_cmd_ticks.replace(1);
-            } else {
-                unreachable!("Unknown command: {}", cmd)
+            match self.instructions.next()? {
+                Instruction::Addx(val) => {
+                    self.next_cmd_value.replace(*val as i64);
+                    self.next_cmd_ticks.replace(2);
+                }
+                Instruction::Noop => {
+                    self.next_cmd_value.take();
+                    self.next_cmd_ticks.replace(1);
+                }
             }
         }
         Some(())
@@ -93,7 +119,7 @@ where
 
 impl<'a, I> Iterator for ElvesCpuCommands<'a, I>
 where
-    I: Iterator<Item=&'a str>,
+    I: Iterator<Item=&'a Instruction>,
 {
     type Item = i64;
     fn next(&mut self) -> Option<Self::Item> {
@@ -104,24 +130,24 @@ where
     }
 }
 
-trait IteratorExt<'a>: Iterator<Item=&'a str>
+trait IteratorExt<'a>: Iterator<Item=&'a Instruction>
 where
     Self: Sized,
 {
-    fn into_elves_cpu_cmd_iterator(&'a mut self) -> ElvesCpuCommands<'a, Self> {
-        ElvesCpuCommands { lines: self, reg_x: 1, next_cmd_ticks: None, next_cmd_value: None }
+    fn elves_cpu_cmd_iterator(&'a mut self) -> ElvesCpuCommands<'a, Self> {
+        ElvesCpuCommands { instructions: self, reg_x: 1, next_cmd_ticks: None, next_cmd_value: None }
     }
 }
 
 impl<'a, T> IteratorExt<'a> for T
 where
-    T: Iterator<Item=&'a str>
+    T: Iterator<Item=&'a Instruction>
 {}
 
 
 #[cfg(test)]
 mod test {
-    use super::main;
+    use super::{parse, part1, part2};
 
     static DATA: &str = r#"addx 15
 addx -11
@@ -272,15 +298,20 @@ noop"#;
 
     #[test]
     fn solution() {
-        let res = main(DATA).expect("invalid input");
-        let part2 = r#"##..##..##..##..##..##..##..##..##..##..
+        let image = r#"##..##..##..##..##..##..##..##..##..##..
 ###...###...###...###...###...###...###.
 ####....####....####....####....####....
 #####.....#####.....#####.....#####.....
 ######......######......######......####
 #######.......#######.......#######.....
-"#.to_string();
-        assert_eq!(res, (13140, part2));
+"#;
+        let program = parse(DATA).expect("invalid input");
+        assert_eq!(part1(&program).expect("invalid input"), 13140);
+        assert_eq!(part2(&program).expect("invalid input"), image);
     }
-}
 
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
+    }
+}
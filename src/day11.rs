@@ -1,111 +1,384 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
 use anyhow::anyhow as err;
 
-use std::str::FromStr;
+use crate::parser::{self, ParseError};
+use crate::solution::{Answer, Solution};
 
-pub fn main(data: &str) -> anyhow::Result<(usize, usize)> {
+pub fn parse(data: &str) -> anyhow::Result<Vec<Monkey>> {
     let mut it = data.lines();
+    let mut lineno = 0;
     let mut monkeys = Vec::new();
-    while let Some(l) = it.next() {
-        if l.is_empty() {
+    while let Some(line) = it.next() {
+        lineno += 1;
+        if line.is_empty() {
             continue
         }
-        let MonkeyLine(n) = l.parse()?;
-        let StartingLine(items) = it.next().ok_or_else(|| err!("more line expected"))?.parse()?;
-        let OperationLine(operation) = it.next().ok_or_else(|| err!("more line expected"))?.parse()?;
-        let TestLine(divisible_by) = it.next().ok_or_else(|| err!("more line expected"))?.parse()?;
-        let TrueLine(on_true) = it.next().ok_or_else(|| err!("more line expected"))?.parse()?;
-        let FalseLine(on_false) = it.next().ok_or_else(|| err!("more line expected"))?.parse()?;
-
-        assert!(n == monkeys.len());
+        let MonkeyLine(n) = line.parse().map_err(|e: ParseError| e.line(lineno))?;
+        if n != monkeys.len() {
+            let message = format!("expected monkey {}", monkeys.len());
+            return Err(ParseError::at(line, line, message).line(lineno).into());
+        }
+        let StartingLine(items) = next_line(&mut it, &mut lineno)?;
+        let OperationLine(operation) = next_line(&mut it, &mut lineno)?;
+        let TestLine(divisible_by) = next_line(&mut it, &mut lineno)?;
+        let TrueLine(on_true) = next_line(&mut it, &mut lineno)?;
+        let FalseLine(on_false) = next_line(&mut it, &mut lineno)?;
+        if on_true == n || on_false == n {
+            return Err(err!("monkey {} throws items to itself", n));
+        }
         monkeys.push(Monkey { items, operation, divisible_by, on_true, on_false });
     }
+    if let Some(m) = monkeys.iter().find(|m| m.on_true.max(m.on_false) >= monkeys.len()) {
+        return Err(err!("no monkey {} to throw items to", m.on_true.max(m.on_false)));
+    }
+    Ok(monkeys)
+}
+
+pub fn part1(monkeys: &[Monkey]) -> anyhow::Result<usize> {
+    let mut game = MonkeyBusiness::<i64, _>::new(monkeys, Divide::new(3)?)?;
+    game.run(20)?;
+    game.level()
+}
 
-    let mut monkeys2 = monkeys.clone();
-    let mut counts2 = [0].repeat(monkeys.len());
+pub fn part2(monkeys: &[Monkey]) -> anyhow::Result<usize> {
+    let mut game = MonkeyBusiness::<Modular, _>::new(monkeys, NoRelief)?;
+    game.run(10_000)?;
+    game.level()
+}
 
-    let mut counts = [0].repeat(monkeys.len());
-    for _ in 0..20 {
-        for idx in 0..monkeys.len() {
-            while let Some(n) = monkeys[idx].items.pop() {
-                counts[idx] += 1;
-                let (i, x) = monkeys[idx].inspect(n);
-                monkeys[i].items.push(x);
-            }
+/// Checks for `rounds` that keeping worry levels modulo the product of the divisors
+/// throws the items exactly as with the actual worry levels relieved by `relief`.
+pub fn verify_modular<F>(monkeys: &[Monkey], relief: F, rounds: usize) -> anyhow::Result<()>
+where
+    F: Fn(&Big) -> Big,
+{
+    let mut modular = MonkeyBusiness::<Modular, _>::new(monkeys, NoRelief)?;
+    let mut exact = MonkeyBusiness::<Big, _>::new(monkeys, |w: Big| Ok(relief(&w)))?;
+    for round in 1..=rounds {
+        let (mut a, mut b) = (Vec::new(), Vec::new());
+        modular.round(|from, to| a.push((from, to)))?;
+        exact.round(|from, to| b.push((from, to)))?;
+        if a != b {
+            return Err(err!("worry levels modulo the divisors throw items differently in round {}", round));
         }
     }
-    counts.sort();
-    let part1 = counts.iter().rev().take(2).product();
+    Ok(())
+}
 
-    let lcm = monkeys2.iter().map(|m| m.divisible_by).product();
-    for _ in 0..10_000 {
-        for idx in 0..monkeys2.len() {
-            while !monkeys2[idx].items.is_empty() {
-                let n = monkeys2[idx].items.remove(0);
-                counts2[idx] += 1;
-                let (i, x) = monkeys2[idx].inspect_v2(n, lcm);
-                monkeys2[i].items.push(x);
-            }
-        }
+pub struct Solver;
+
+impl Solution for Solver {
+    type Input = Vec<Monkey>;
+
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Monkey business (20 rounds)", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Monkey business (10000 rounds)", part2(input)?))
     }
-    counts2.sort();
-    let part2 = counts2.iter().rev().take(2).product();
-    Ok((part1, part2))
 }
 
 #[derive(Debug, Clone)]
-struct Monkey {
+pub struct Monkey {
     items: Vec<i64>,
     operation: Operation,
     divisible_by: i64,
     on_true: usize,
     on_false: usize,
 }
-impl Monkey {
-    fn inspect(&self, item: i64) -> (usize, i64) {
-        let out = self.operation.apply(item);
-        let out = out / 3;
-        let idx = if out % self.divisible_by == 0 {
-            self.on_true
-        } else {
-            self.on_false
-        };
-        (idx, out)
+
+/// What happens to the worry level of an item after a monkey inspects it.
+///
+/// Any `Fn(W) -> anyhow::Result<W>` closure is a relief rule.
+pub trait Relief<W> {
+    fn relieve(&self, worry: W) -> anyhow::Result<W>;
+
+    /// Whether worry levels are left as they are.
+    fn is_identity(&self) -> bool {
+        false
+    }
+}
+
+impl<W, F: Fn(W) -> anyhow::Result<W>> Relief<W> for F {
+    fn relieve(&self, worry: W) -> anyhow::Result<W> {
+        self(worry)
+    }
+}
+
+/// No relief, worry levels are left as they are.
+#[derive(Debug, Clone, Copy)]
+pub struct NoRelief;
+
+impl<W> Relief<W> for NoRelief {
+    fn relieve(&self, worry: W) -> anyhow::Result<W> {
+        Ok(worry)
+    }
+    fn is_identity(&self) -> bool {
+        true
+    }
+}
+
+/// Worry levels divided by a positive number, rounding down.
+#[derive(Debug, Clone, Copy)]
+pub struct Divide(i64);
+
+impl Divide {
+    pub fn new(n: i64) -> anyhow::Result<Divide> {
+        if n <= 0 {
+            return Err(err!("relief must divide worry levels by a positive number, not {}", n));
+        }
+        Ok(Divide(n))
+    }
+}
+
+impl Relief<i64> for Divide {
+    fn relieve(&self, worry: i64) -> anyhow::Result<i64> {
+        Ok(worry / self.0)
+    }
+}
+
+impl Relief<Big> for Divide {
+    fn relieve(&self, worry: Big) -> anyhow::Result<Big> {
+        Ok(worry.div_rem(self.0).0)
+    }
+}
+
+/// Simulation of the monkeys throwing items around, with worry levels represented as `W`
+/// and relieved by the rule `R`.
+#[derive(Debug)]
+pub struct MonkeyBusiness<'a, W, R = NoRelief> {
+    monkeys: &'a [Monkey],
+    items: Vec<VecDeque<W>>,
+    relief: R,
+    inspections: Vec<usize>,
+}
+
+impl<'a, W: Worry, R: Relief<W>> MonkeyBusiness<'a, W, R> {
+    pub fn new(monkeys: &'a [Monkey], relief: R) -> anyhow::Result<Self> {
+        if !W::ANY_RELIEF && !relief.is_identity() {
+            return Err(err!("{} worry levels only work without relief", std::any::type_name::<W>()));
+        }
+        let items = monkeys.iter()
+            .map(|m| m.items.iter().map(|&item| W::new(item, monkeys)).collect())
+            .collect::<anyhow::Result<_>>()?;
+        Ok(MonkeyBusiness { monkeys, items, relief, inspections: vec![0; monkeys.len()] })
+    }
+
+    /// Plays a round, calling `throw` with both monkeys for every item thrown.
+    pub fn round(&mut self, mut throw: impl FnMut(usize, usize)) -> anyhow::Result<()> {
+        for (idx, monkey) in self.monkeys.iter().enumerate() {
+            while let Some(item) = self.items[idx].pop_front() {
+                self.inspections[idx] += 1;
+                let item = self.relief.relieve(item.apply(monkey.operation)?)?;
+                let target = if item.is_divisible_by(monkey.divisible_by) {
+                    monkey.on_true
+                } else {
+                    monkey.on_false
+                };
+                self.items[target].push_back(item);
+                throw(idx, target);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn run(&mut self, rounds: usize) -> anyhow::Result<()> {
+        for _ in 0..rounds {
+            self.round(|_, _| ())?;
+        }
+        Ok(())
+    }
+
+    /// Number of items inspected by every monkey so far.
+    pub fn inspections(&self) -> &[usize] {
+        &self.inspections
+    }
+
+    /// Product of the inspections of the two most active monkeys.
+    pub fn level(&self) -> anyhow::Result<usize> {
+        let mut counts = self.inspections.clone();
+        counts.sort_unstable();
+        counts.iter().rev().take(2)
+            .try_fold(1usize, |acc, &n| acc.checked_mul(n))
+            .ok_or_else(|| err!("monkey business level overflow"))
+    }
+}
+
+/// Representation of worry levels.
+pub trait Worry: Sized {
+    /// Whether the items are thrown right with any relief rule, or only without relief.
+    const ANY_RELIEF: bool = true;
+
+    /// Worry level of a starting item of one of the `monkeys`.
+    fn new(item: i64, monkeys: &[Monkey]) -> anyhow::Result<Self>;
+    fn apply(&self, operation: Operation) -> anyhow::Result<Self>;
+    fn is_divisible_by(&self, divisor: i64) -> bool;
+}
+
+/// Actual worry levels, as long as they fit.
+impl Worry for i64 {
+    fn new(item: i64, _monkeys: &[Monkey]) -> anyhow::Result<Self> {
+        Ok(item)
+    }
+    fn apply(&self, operation: Operation) -> anyhow::Result<Self> {
+        operation.apply(*self)
+    }
+    fn is_divisible_by(&self, divisor: i64) -> bool {
+        self % divisor == 0
+    }
+}
+
+/// Worry level modulo the product of the divisors of all the monkeys, which keeps
+/// the divisibility tests right as long as there is no relief.
+#[derive(Debug, Clone, Copy)]
+pub struct Modular {
+    value: i64,
+    modulus: i64,
+}
+
+impl Worry for Modular {
+    const ANY_RELIEF: bool = false;
+
+    fn new(item: i64, monkeys: &[Monkey]) -> anyhow::Result<Self> {
+        let modulus = monkeys.iter()
+            .try_fold(1i64, |acc, m| acc.checked_mul(m.divisible_by))
+            .ok_or_else(|| err!("divisors product overflows"))?;
+        Ok(Modular { value: item % modulus, modulus })
+    }
+    fn apply(&self, operation: Operation) -> anyhow::Result<Self> {
+        Ok(Modular { value: operation.apply(self.value)? % self.modulus, ..*self })
+    }
+    fn is_divisible_by(&self, divisor: i64) -> bool {
+        self.value % divisor == 0
+    }
+}
+
+/// Non-negative worry level of any size, as 32 bits digits with the least significant first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Big(Vec<u32>);
+
+impl Big {
+    pub fn from_i64(n: i64) -> anyhow::Result<Big> {
+        let n = u64::try_from(n).map_err(|_| err!("negative worry level {}", n))?;
+        let mut big = Big(vec![n as u32, (n >> 32) as u32]);
+        big.trim();
+        Ok(big)
+    }
+
+    fn trim(&mut self) {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
     }
 
-    fn inspect_v2(&self, item: i64, lcm: i64) -> (usize, i64) {
-        let out = self.operation.apply(item);
-        let idx = if out % self.divisible_by == 0 {
-            self.on_true
-        } else {
-            self.on_false
+    pub fn add(&self, other: &Big) -> Big {
+        let digit = |big: &Big, i| big.0.get(i).copied().unwrap_or(0) as u64;
+        let mut digits = Vec::with_capacity(self.0.len().max(other.0.len()) + 1);
+        let mut carry = 0;
+        for i in 0..self.0.len().max(other.0.len()) {
+            let sum = digit(self, i) + digit(other, i) + carry;
+            digits.push(sum as u32);
+            carry = sum >> 32;
+        }
+        digits.push(carry as u32);
+        let mut big = Big(digits);
+        big.trim();
+        big
+    }
+
+    pub fn mul(&self, other: &Big) -> Big {
+        let mut digits = vec![0u32; self.0.len() + other.0.len()];
+        for (i, &a) in self.0.iter().enumerate() {
+            let mut carry = 0;
+            for (j, &b) in other.0.iter().enumerate() {
+                let product = digits[i + j] as u64 + a as u64 * b as u64 + carry;
+                digits[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            digits[i + other.0.len()] = carry as u32;
+        }
+        let mut big = Big(digits);
+        big.trim();
+        big
+    }
+
+    /// Quotient and remainder of the division by a positive `divisor`.
+    pub fn div_rem(&self, divisor: i64) -> (Big, i64) {
+        let divisor = divisor as u128;
+        let mut digits = vec![0u32; self.0.len()];
+        let mut rem = 0u128;
+        for (i, &d) in self.0.iter().enumerate().rev() {
+            let n = rem << 32 | d as u128;
+            digits[i] = (n / divisor) as u32;
+            rem = n % divisor;
+        }
+        let mut big = Big(digits);
+        big.trim();
+        (big, rem as i64)
+    }
+}
+
+impl Worry for Big {
+    fn new(item: i64, _monkeys: &[Monkey]) -> anyhow::Result<Self> {
+        Big::from_i64(item)
+    }
+    fn apply(&self, operation: Operation) -> anyhow::Result<Self> {
+        let arg = |arg| match arg {
+            Arg::Old => Ok(self.clone()),
+            Arg::Val(x) => Big::from_i64(x),
         };
-        (idx, out % lcm)
+        match operation {
+            Operation::Add(x) => Ok(self.add(&arg(x)?)),
+            Operation::Mult(x) => Ok(self.mul(&arg(x)?)),
+        }
+    }
+    fn is_divisible_by(&self, divisor: i64) -> bool {
+        self.div_rem(divisor).1 == 0
     }
 }
 
 #[derive(Debug, Clone, Copy)]
-enum Operation {
+pub enum Operation {
     Add(Arg),
     Mult(Arg),
 }
 impl Operation {
-    fn apply(&self, item: i64) -> i64 {
+    fn apply(&self, item: i64) -> anyhow::Result<i64> {
         use Operation::*;
         use Arg::*;
-        match self {
-            Add(Old) => item + item,
-            Add(Val(x)) => item + x,
-            Mult(Old) => item * item,
-            Mult(Val(x)) => item * x,
-        }
+        let out = match self {
+            Add(Old) => item.checked_add(item),
+            Add(Val(x)) => item.checked_add(*x),
+            Mult(Old) => item.checked_mul(item),
+            Mult(Val(x)) => item.checked_mul(*x),
+        };
+        out.ok_or_else(|| err!("worry level overflow"))
     }
 }
 #[derive(Debug, Clone, Copy)]
-enum Arg {
+pub enum Arg {
     Old,
     Val(i64),
 }
 
+/// Parses the next line of the notes about a monkey.
+fn next_line<'a, T, I>(it: &mut I, lineno: &mut usize) -> Result<T, ParseError>
+where
+    T: FromStr<Err=ParseError>,
+    I: Iterator<Item=&'a str>,
+{
+    *lineno += 1;
+    let line = it.next()
+        .ok_or_else(|| ParseError::at_end("", "expected more notes about the monkey").line(*lineno))?;
+    line.parse().map_err(|e: ParseError| e.line(*lineno))
+}
+
 struct MonkeyLine(usize);
 struct StartingLine(Vec<i64>);
 struct OperationLine(Operation);
@@ -114,71 +387,108 @@ struct TrueLine(usize);
 struct FalseLine(usize);
 
 impl FromStr for MonkeyLine {
-    type Err = anyhow::Error;
-    fn from_str(val: &str) -> anyhow::Result<Self> {
-        assert!(val.starts_with("Monkey "));
-        let idx = val["Monkey ".len()..val.len() - 1].parse()?;
-        Ok(MonkeyLine(idx))
+    type Err = ParseError;
+    fn from_str(val: &str) -> Result<Self, ParseError> {
+        let rest = parser::prefix(val, val, "Monkey ")?;
+        let idx = rest.strip_suffix(':').ok_or_else(|| ParseError::at_end(val, "expected ':'"))?;
+        Ok(MonkeyLine(parser::number(val, idx)?))
     }
 }
 impl FromStr for StartingLine {
-    type Err = anyhow::Error;
-    fn from_str(val: &str) -> anyhow::Result<Self> {
-        assert!(val.starts_with("  Starting items: "));
-        let items = val["  Starting items: ".len()..]
-            .split(',')
-            .map(|s| s.trim().parse())
-            .collect::<Result<Vec<i64>, std::num::ParseIntError>>()?;
+    type Err = ParseError;
+    fn from_str(val: &str) -> Result<Self, ParseError> {
+        let items = parser::prefix(val, val, "  Starting items: ")?
+            .split(", ")
+            .map(|item| parser::number(val, item))
+            .collect::<Result<Vec<i64>, _>>()?;
         Ok(StartingLine(items))
     }
 }
 impl FromStr for OperationLine {
-    type Err = anyhow::Error;
-    fn from_str(val: &str) -> anyhow::Result<Self> {
-        assert!(val.starts_with("  Operation: new = old "));
-        let (op, arg) = val["  Operation: new = old ".len()..]
-            .split_once(" ")
-            .ok_or_else(|| err!("space expected"))?;
+    type Err = ParseError;
+    fn from_str(val: &str) -> Result<Self, ParseError> {
+        let rest = parser::prefix(val, val, "  Operation: new = old ")?;
+        let (op, arg) = parser::split(val, rest, " ")?;
         let arg = match arg {
             "old" => Arg::Old,
-            x => Arg::Val(x.parse()?),
+            x => Arg::Val(parser::number(val, x)?),
         };
         let op = match op {
             "*" => Operation::Mult(arg),
             "+" => Operation::Add(arg),
-            x => return Err(err!("unknown operation {}", x))
+            x => return Err(ParseError::at(val, x, "expected operation '*' or '+'"))
         };
         Ok(OperationLine(op))
     }
 }
 impl FromStr for TestLine {
-    type Err = anyhow::Error;
-    fn from_str(val: &str) -> anyhow::Result<Self> {
-        assert!(val.starts_with("  Test: divisible by "));
-        let i = val["  Test: divisible by ".len()..].parse()?;
-        Ok(TestLine(i))
+    type Err = ParseError;
+    fn from_str(val: &str) -> Result<Self, ParseError> {
+        let i = parser::prefix(val, val, "  Test: divisible by ")?;
+        match parser::number(val, i)? {
+            n if n > 0 => Ok(TestLine(n)),
+            _ => Err(ParseError::at(val, i, "expected a positive divisor")),
+        }
     }
 }
 impl FromStr for TrueLine {
-    type Err = anyhow::Error;
-    fn from_str(val: &str) -> anyhow::Result<Self> {
-        assert!(val.starts_with("    If true: throw to monkey "));
-        let i = val["    If true: throw to monkey ".len()..].parse()?;
-        Ok(TrueLine(i))
+    type Err = ParseError;
+    fn from_str(val: &str) -> Result<Self, ParseError> {
+        let i = parser::prefix(val, val, "    If true: throw to monkey ")?;
+        Ok(TrueLine(parser::number(val, i)?))
     }
 }
 impl FromStr for FalseLine {
-    type Err = anyhow::Error;
-    fn from_str(val: &str) -> anyhow::Result<Self> {
-        assert!(val.starts_with("    If false: throw to monkey "));
-        let i = val["    If false: throw to monkey ".len()..].parse()?;
-        Ok(FalseLine(i))
+    type Err = ParseError;
+    fn from_str(val: &str) -> Result<Self, ParseError> {
+        let i = parser::prefix(val, val, "    If false: throw to monkey ")?;
+        Ok(FalseLine(parser::number(val, i)?))
+    }
+}
+
+impl fmt::Display for MonkeyLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Monkey {}:", self.0)
+    }
+}
+impl fmt::Display for StartingLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let items: Vec<_> = self.0.iter().map(i64::to_string).collect();
+        write!(f, "  Starting items: {}", items.join(", "))
+    }
+}
+impl fmt::Display for OperationLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (op, arg) = match self.0 {
+            Operation::Add(arg) => ('+', arg),
+            Operation::Mult(arg) => ('*', arg),
+        };
+        match arg {
+            Arg::Old => write!(f, "  Operation: new = old {} old", op),
+            Arg::Val(x) => write!(f, "  Operation: new = old {} {}", op, x),
+        }
+    }
+}
+impl fmt::Display for TestLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "  Test: divisible by {}", self.0)
+    }
+}
+impl fmt::Display for TrueLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "    If true: throw to monkey {}", self.0)
+    }
+}
+impl fmt::Display for FalseLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "    If false: throw to monkey {}", self.0)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::main;
+    use super::*;
+    use crate::fuzz::{self, Rng};
 
     static DATA: &str = r#"Monkey 0:
   Starting items: 79, 98
@@ -210,7 +520,116 @@ Monkey 3:
 
     #[test]
     fn solution() {
-        let res = main(DATA).expect("invalid input");
-        assert_eq!(res, (10605, 2713310158));
+        let monkeys = parse(DATA).expect("invalid input");
+        assert_eq!(part1(&monkeys).expect("invalid input"), 10605);
+        assert_eq!(part2(&monkeys).expect("invalid input"), 2713310158);
+    }
+
+    #[test]
+    fn big_numbers() {
+        let monkeys = parse(DATA).expect("invalid input");
+        let mut game = MonkeyBusiness::<Big, _>::new(&monkeys, Divide::new(3).expect("positive")).expect("invalid input");
+        game.run(20).expect("invalid input");
+        assert_eq!(game.inspections(), [101, 95, 7, 105]);
+        assert_eq!(game.level().expect("invalid input"), 10605);
+
+        let mut rng = Rng::new(11);
+        for _ in 0..1000 {
+            let (a, b) = (rng.next() >> 1, rng.next() >> 1);
+            let big = |n: u64| Big::from_i64(n as i64).expect("non-negative");
+            let sum = big(a).add(&big(b));
+            let product = big(a).mul(&big(b));
+            let (divisor, expected) = (rng.next() as i64 >> (1 + rng.below(62)), a as u128 * b as u128);
+            let (quotient, rem) = product.div_rem(divisor.max(1));
+            assert_eq!(sum.div_rem(1 << 62), (big((a + b) >> 62), ((a + b) % (1 << 62)) as i64));
+            assert_eq!(rem as u128, expected % divisor.max(1) as u128);
+            assert_eq!(quotient.mul(&big(divisor.max(1) as u64)).add(&big(rem as u64)), product);
+        }
+    }
+
+    #[test]
+    fn modular() {
+        let monkeys = parse(DATA).expect("invalid input");
+        verify_modular(&monkeys, Big::clone, 12).expect("modular worry levels");
+        let err = MonkeyBusiness::<Modular, _>::new(&monkeys, |w: Modular| Ok(w)).err().expect("relief");
+        assert!(err.to_string().contains("without relief"), "{}", err);
+        assert!(Divide::new(0).is_err());
+        let notes = "Monkey 0:\n  Starting items: 36\n  Operation: new = old * 1\n  Test: divisible by 2\n    \
+                     If true: throw to monkey 1\n    If false: throw to monkey 2\n\n\
+                     Monkey 1:\n  Starting items: 1\n  Operation: new = old * 1\n  Test: divisible by 3\n    \
+                     If true: throw to monkey 0\n    If false: throw to monkey 2\n\n\
+                     Monkey 2:\n  Starting items: 1\n  Operation: new = old * 1\n  Test: divisible by 5\n    \
+                     If true: throw to monkey 0\n    If false: throw to monkey 1";
+        let monkeys = parse(notes).expect("invalid input");
+        // monkey 1 throws its item 1 to monkey 0 once relieved to 1 / 3 = 0, but to monkey 2 without relief
+        let err = verify_modular(&monkeys, |w| w.div_rem(3).0, 1).expect_err("division is not modular");
+        assert!(err.to_string().contains("differently"), "{}", err);
+    }
+
+    #[test]
+    fn custom_relief() {
+        let monkeys = parse(DATA).expect("invalid input");
+        let mut game = MonkeyBusiness::<i64, _>::new(&monkeys, |w: i64| Ok(w / 3)).expect("invalid input");
+        game.run(20).expect("invalid input");
+        assert_eq!(game.level().expect("invalid input"), 10605);
+
+        // a closure relieves like the built-in rule
+        let mut halved = MonkeyBusiness::<i64, _>::new(&monkeys, |w: i64| Ok(w / 2)).expect("invalid input");
+        let mut divided = MonkeyBusiness::<i64, _>::new(&monkeys, Divide::new(2).expect("positive")).expect("invalid input");
+        halved.run(5).expect("invalid input");
+        divided.run(5).expect("invalid input");
+        assert_eq!(halved.inspections(), divided.inspections());
+        assert!(MonkeyBusiness::<i64, _>::new(&monkeys, |_: i64| Err(anyhow::anyhow!("no")))
+            .expect("invalid input").run(1).is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
+    }
+
+    /// Random number, negative or positive, of any magnitude.
+    fn number(rng: &mut Rng) -> i64 {
+        rng.next() as i64 >> rng.below(64)
+    }
+
+    /// Notes about 2 to 6 monkeys.
+    fn notes(rng: &mut Rng) -> String {
+        let count = 2 + rng.below(5);
+        let monkeys: Vec<_> = (0..count).map(|idx| {
+            let items: Vec<_> = (0..1 + rng.below(5)).map(|_| number(rng).to_string()).collect();
+            let op = *rng.pick(&['+', '*']);
+            let arg = match rng.below(2) {
+                0 => "old".to_string(),
+                _ => number(rng).to_string(),
+            };
+            let divisor = (rng.next() >> rng.below(64)).clamp(1, i64::MAX as u64);
+            let mut target = || (idx + 1 + rng.below(count - 1)) % count;
+            format!(
+                "Monkey {}:\n  Starting items: {}\n  Operation: new = old {} {}\n  \
+                 Test: divisible by {}\n    If true: throw to monkey {}\n    If false: throw to monkey {}",
+                idx, items.join(", "), op, arg, divisor, target(), target(),
+            )
+        }).collect();
+        monkeys.join("\n\n")
+    }
+
+    #[test]
+    fn round_trip() {
+        fuzz::round_trip(11, notes, |data| {
+            let monkeys = parse(data)?;
+            let notes: Vec<_> = monkeys.into_iter().enumerate().map(|(idx, m)| {
+                let lines = [
+                    MonkeyLine(idx).to_string(),
+                    StartingLine(m.items).to_string(),
+                    OperationLine(m.operation).to_string(),
+                    TestLine(m.divisible_by).to_string(),
+                    TrueLine(m.on_true).to_string(),
+                    FalseLine(m.on_false).to_string(),
+                ];
+                lines.join("\n")
+            }).collect();
+            Ok::<_, anyhow::Error>(notes.join("\n\n"))
+        });
     }
 }
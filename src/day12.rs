@@ -1,208 +1,193 @@
 use std::str;
-use std::collections::{HashSet, HashMap, VecDeque};
+use std::collections::HashSet;
 use std::io;
-use std::{thread, time};
+use std::time;
 
 use anyhow::anyhow as err;
-use crate::graph;
-
-pub fn main(data: &str) -> anyhow::Result<(usize, usize)> {
-    let map: Map = data.parse()?;
-
-    let t0 = time::Instant::now();
-    bms(&map, map.start(), map.end(), false)
-        .ok_or_else(|| err!("could not find path"))?;
-    let dt1 = t0.elapsed();
-    let t0 = time::Instant::now();
-    build_path(&map, map.start(), map.end(), false)
-        .ok_or_else(|| err!("could not find path"))?;
-    let dt2 = t0.elapsed();
-
-    draw_map(&map)?;
-
-    let path = bms(&map, map.start(), map.end(), true)
-    // let path = build_path(&map, map.start(), map.end(), false)
-        .ok_or_else(|| err!("could not find path"))?;
-
-    let result1 = rebuild_path(path, map.end());
-    draw_path(&map, &result1, graph::COLOR_RED, false)?;
-
-    thread::sleep(time::Duration::from_secs(5));
-
-    draw_map(&map)?;
-    let path = build_path(&map, map.start(), map.end(), true)
-        .ok_or_else(|| err!("could not find path"))?;
-
-    let result = rebuild_path(path, map.end());
-    draw_path(&map, &result, graph::COLOR_GREEN, false)?;
-
-    thread::sleep(time::Duration::from_secs(2));
-
-    draw_map(&map)?;
-    draw_path(&map, &result1, graph::COLOR_RED, true)?;
-    draw_path(&map, &result, graph::COLOR_GREEN, true)?;
-    println!("\x1b[{};1HDone", map.height + 1);
-
-    println!("Bms: RED:   took: {:?}", dt1);
-    println!("A*:  GREEN: took: {:?}", dt2);
-
-    let starts: Vec<_> = (0..map.height)
-        .into_iter()
-        .flat_map(|y| (0..map.width).into_iter().map(move |x| Point { x, y }))
-        .filter(|p| map.h(p) == 0)
-        .collect();
-
-    let mut min = usize::MAX;
-    for start in starts.into_iter() {
-        if let Some(path) = bms(&map, start, map.end(), false) {
-            let path = rebuild_path(path, map.end());
-            let steps = path.len();
-            if steps < min {
-                min = steps;
-            }
-        }
-    }
+use crate::bench::Stats;
+use crate::graph::{self, Renderer};
+use crate::grid::Grid;
+use crate::parser::ParseError;
+use crate::runner::format_duration;
+use crate::search;
+use crate::solution::{Answer, Solution};
+
+pub fn part1(map: &Map) -> anyhow::Result<usize> {
+    let (_, steps) = search::a_star(
+        map.start(),
+        |&p| p == map.end(),
+        |p: &Point| p.allowed_neigbours(map).map(|n| (n, 1)),
+        climb_heuristic(map, map.end()),
+    ).ok_or_else(|| err!("could not find path"))?;
+    Ok(steps)
+}
 
-    Ok((result.len() - 1, min - 1))
+/// Searches backwards from `E` for the closest square at the lowest elevation.
+pub fn part2(map: &Map) -> anyhow::Result<usize> {
+    let path = search::bfs(map.end(), |p| map.h(p) == 0, |&p: &Point| {
+        // the step from n to p has to be allowed
+        p.neighbours(map).filter(move |n| map.delta_height(&p, n) <= 1)
+    }).ok_or_else(|| err!("could not find path"))?;
+    Ok(path.len() - 1)
 }
 
-fn rebuild_path(pathes: HashMap<Point, Point>, goal: Point) -> Vec<Point> {
-    let mut result = Vec::new();
-    result.push(goal);
-    let mut prev = goal;
-    while let Some(&p) = pathes.get(&prev) {
-        result.push(p);
-        prev = p;
+/// Animates BFS and A* path search, then benchmarks them.
+pub fn visualize(map: &Map, out: &mut dyn Renderer) -> anyhow::Result<()> {
+    draw_map(out, map)?;
+
+    let mut drawn = HashSet::new();
+    let result1 = search::bfs(map.start(), |&p| p == map.end(), |p: &Point| {
+        highlight(out, map, &mut drawn, p.allowed_neigbours(map).collect())
+    }).ok_or_else(|| err!("could not find path"))?;
+    draw_path(out, map, &result1, graph::COLOR_RED, false)?;
+
+    out.pause(time::Duration::from_secs(5));
+
+    draw_map(out, map)?;
+    drawn.clear();
+    let (result, _) = search::a_star(
+        map.start(),
+        |&p| p == map.end(),
+        |p: &Point| highlight(out, map, &mut drawn, p.allowed_neigbours(map).collect()).into_iter().map(|n| (n, 1)),
+        climb_heuristic(map, map.end()),
+    ).ok_or_else(|| err!("could not find path"))?;
+    draw_path(out, map, &result, graph::COLOR_GREEN, false)?;
+
+    out.pause(time::Duration::from_secs(2));
+
+    draw_map(out, map)?;
+    draw_path(out, map, &result1, graph::COLOR_RED, true)?;
+    draw_path(out, map, &result, graph::COLOR_GREEN, true)?;
+    graph::goto_line(out, map.heights.height())?;
+    out.write_line("Done")?;
+
+    for (name, stats) in benchmark(map, BENCH_ITERATIONS)? {
+        out.write_line(&format!("{:<16} median {:>10}  min {:>10}", name, format_duration(stats.median), format_duration(stats.min)))?;
     }
-    result
+    Ok(())
 }
 
-fn bms(map: &Map, start: Point, goal: Point, draw: bool) -> Option<HashMap<Point, Point>> {
-    let mut result = HashMap::new();
-    let mut queue = VecDeque::new();
-    queue.push_back(start);
-    let mut seen = HashSet::new();
-    seen.insert(start);
-    while let Some(p) = queue.pop_front() {
-        if p == goal {
-            return Some(result)
-        }
-        for n in p.allowed_neigbours(map) {
-            if seen.contains(&n) {
-                continue
-            }
-            seen.insert(n);
-            result.insert(n, p);
-            queue.push_back(n);
-            if draw {
-                graph::delay_draw_char(
-                    n.x, n.y, ((map.h(&n) + b'a') as char, graph::HIGHLIGHT)).ok();
+const BENCH_ITERATIONS: usize = 100;
+
+/// Times every search from `S` to `E` over `iterations` runs, without drawing,
+/// checking that all of them find paths of the same length.
+pub fn benchmark(map: &Map, iterations: usize) -> anyhow::Result<Vec<(&'static str, Stats)>> {
+    type Search = fn(&Map) -> Option<Vec<Point>>;
+    let searches: [(&str, Search); 3] = [
+        ("BFS (RED)", |map| {
+            search::bfs(map.start(), |&p| p == map.end(), |p: &Point| p.allowed_neigbours(map))
+        }),
+        ("Dijkstra", |map| {
+            search::dijkstra(map.start(), |&p| p == map.end(), |p: &Point| p.allowed_neigbours(map).map(|n| (n, 1)))
+                .map(|(path, _)| path)
+        }),
+        ("A* (GREEN)", |map| {
+            let steps = |p: &Point| p.allowed_neigbours(map).map(|n| (n, 1));
+            search::a_star(map.start(), |&p| p == map.end(), steps, climb_heuristic(map, map.end()))
+                .map(|(path, _)| path)
+        }),
+    ];
+    let mut results = Vec::new();
+    let mut steps = None;
+    for (name, search) in searches {
+        let mut samples = Vec::with_capacity(iterations);
+        for _ in 0..iterations.max(1) {
+            let t0 = time::Instant::now();
+            let path = search(map).ok_or_else(|| err!("could not find path"))?;
+            samples.push(t0.elapsed());
+            let expected = *steps.get_or_insert(path.len());
+            if path.len() != expected {
+                return Err(err!("{} found a path of {} steps instead of {}", name, path.len() - 1, expected - 1));
             }
         }
+        let stats = Stats::new(&samples).ok_or_else(|| err!("no samples collected"))?;
+        results.push((name, stats));
     }
-    None
-}
-
-// reuse code from day 15 2021
-fn pop_lowest(
-    set: &HashSet<Point>, scores: &HashMap<Point, isize>,
-) -> Point {
-    set.iter()
-        .min_by_key(|p| scores.get(p).expect("no score"))
-        .expect("empty set")
-        .clone()
+    Ok(results)
 }
 
-fn build_path(map: &Map, start: Point, goal: Point, draw: bool) -> Option<HashMap<Point, Point>> {
-    let mut todo = HashSet::new();
-    todo.insert(start.clone());
+pub struct Solver;
 
-    let mut f_scores = HashMap::new();
-    f_scores.insert(start.clone(), 0isize);
+impl Solution for Solver {
+    type Input = Map;
 
-    let mut g_scores = HashMap::new();
-    g_scores.insert(start.clone(), 0isize);
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        Ok(data.parse()?)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Fewest steps from S", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Fewest steps from any 'a'", part2(input)?))
+    }
+    fn visualize(input: &Self::Input, out: &mut dyn Renderer) -> anyhow::Result<()> {
+        visualize(input, out)
+    }
+}
 
-    let mut path = HashMap::new();
-    while !todo.is_empty() {
-        let current = pop_lowest(&todo, &f_scores);
-        todo.remove(&current);
+/// Lower bound of the steps from a square to `goal`: every step moves by one square
+/// and climbs by at most one.
+fn climb_heuristic(map: &Map, goal: Point) -> impl Fn(&Point) -> usize + '_ {
+    move |p| {
+        let distance = p.x.abs_diff(goal.x) + p.y.abs_diff(goal.y);
+        let climb = map.h(&goal).saturating_sub(map.h(p)) as usize;
+        distance.max(climb)
+    }
+}
 
-        if current == goal {
-            return Some(path)
-        }
-        for n in current.allowed_neigbours(map) {
-            let score = g_scores
-                .get(&current)
-                .map(|s| s + 1)
-                .expect("current has score");
-            if score < *g_scores.get(&n).unwrap_or(&isize::MAX) {
-                path.insert(n, current.clone());
-                g_scores.insert(n, score);
-                f_scores.insert(n, score); // + h_score(&n, &goal));
-                todo.insert(n);
-                if draw {
-                    graph::delay_draw_char(
-                        n.x, n.y, ((map.h(&n) + b'a') as char, graph::HIGHLIGHT)).ok();
-                }
-            }
+/// Highlights the `points` not highlighted yet, returns all of them.
+fn highlight(out: &mut dyn Renderer, map: &Map, drawn: &mut HashSet<Point>, points: Vec<Point>) -> Vec<Point> {
+    for n in &points {
+        if drawn.insert(*n) {
+            graph::delay_draw_char(out, n.x, n.y, ((map.h(n) + b'a') as char, graph::HIGHLIGHT)).ok();
         }
     }
-    None
+    points
 }
 
 #[derive(Debug)]
-struct Map {
-    width: usize,
-    height: usize,
-    data: Vec<u8>,
-    start: usize,
-    end: usize,
+pub struct Map {
+    heights: Grid<u8>,
+    start: Point,
+    end: Point,
 }
 
 impl str::FromStr for Map {
-    type Err = anyhow::Error;
-    fn from_str(val: &str) -> anyhow::Result<Self> {
-        let width = val.find('\n').ok_or_else(|| err!("newline was expected"))?;
-        let start = val.chars()
-            .filter(|c| *c != '\n')
-            .position(|c| c == 'S')
-            .ok_or_else(|| err!("start not found"))?;
-        let end = val.chars()
-            .filter(|c| *c != '\n')
-            .position(|c| c == 'E')
-            .ok_or_else(|| err!("end not found"))?;
-        let data = val.lines()
-            .flat_map(|l| l.as_bytes())
-            .map(|b| match b {
-                b'a'..=b'z' => Ok(b - b'a'),
-                b'S' => Ok(0),
-                b'E' => Ok(b'z' - b'a'),
-                _ => Err(err!("unexpected character")),
-            }).collect::<anyhow::Result<Vec<_>>>()?;
-        let height = data.len() / width;
-        Ok(Map { width, height, data, start, end })
+    type Err = ParseError;
+    fn from_str(val: &str) -> Result<Self, ParseError> {
+        let squares = Grid::parse(val, "expected height 'a'-'z', start 'S' or end 'E'", |c| {
+            matches!(c, 'a'..='z' | 'S' | 'E').then_some(c)
+        })?;
+        let lines = val.lines().count();
+        let last = val.lines().last().unwrap_or_default();
+        let find = |square, message| {
+            squares.find(|&c| c == square)
+                .map(|(x, y)| Point { x, y })
+                .ok_or_else(|| ParseError::at_end(last, message).line(lines))
+        };
+        let start = find('S', "start 'S' not found")?;
+        let end = find('E', "end 'E' not found")?;
+        let heights = squares.map(|&c| match c {
+            'S' => 0,
+            'E' => b'z' - b'a',
+            c => c as u8 - b'a',
+        });
+        Ok(Map { heights, start, end })
     }
 }
 
 impl Map {
     fn start(&self) -> Point {
-        let x = self.start % self.width;
-        let y = self.start / self.width;
-        Point { x, y }
+        self.start
     }
     fn end(&self) -> Point {
-        let x = self.end % self.width;
-        let y = self.end / self.width;
-        Point { x, y }
+        self.end
     }
     fn delta_height(&self, a: &Point, b: &Point) -> isize {
-        let s1 = self.data[a.y * self.width + a.x] as isize;
-        let s2 = self.data[b.y * self.width + b.x] as isize;
-        s1 - s2
+        self.h(a) as isize - self.h(b) as isize
     }
     fn h(&self, p: &Point) -> u8 {
-        self.data[p.y * self.width + p.x]
+        self.heights[(p.x, p.y)]
     }
 }
 
@@ -213,39 +198,26 @@ struct Point {
 }
 
 impl Point {
-    fn neighbours(&self, h: usize, w: usize) -> impl Iterator<Item=Point> {
-        self.neighbours_opt(h, w).filter_map(|p| p)
-    }
-    fn neighbours_opt(&self, h: usize, w: usize) -> impl Iterator<Item=Option<Point>> {
-        let x = self.x as isize;
-        let y = self.y as isize;
-        let h = h as isize;
-        let w = w as isize;
-        [(0, 1), (1, 0), (0, -1), (-1, 0)]
-            .into_iter()
-            .map(move |(dx, dy)| (x + dx, y + dy))
-            .map(move |(x, y)| {
-                (x >= 0 && x < w && y >= 0 && y < h)
-                .then(|| Point { x: x as usize, y: y as usize })
-            })
+    fn neighbours<'a>(&self, map: &'a Map) -> impl Iterator<Item=Point> + 'a {
+        map.heights.neighbours4((self.x, self.y)).map(|(x, y)| Point { x, y })
     }
     fn allowed_neigbours<'a>(&self, map: &'a Map) -> impl Iterator<Item=Point> + 'a {
-        let p1 = self.clone();
-        self.neighbours(map.height, map.width)
+        let p1 = *self;
+        self.neighbours(map)
             .filter(move |p2| map.delta_height(p2, &p1) <= 1)
     }
 }
 
 // Drawing stuf...
 
-fn draw_map(map: &Map) -> io::Result<()> {
-    graph::draw_map(map.width, map.height, |offset| {
-        let c = (map.data[offset] + b'a') as char;
-        let s = map.start();
-        let e = map.end();
-        if offset == s.y * map.width + s.x {
+fn draw_map(out: &mut dyn Renderer, map: &Map) -> io::Result<()> {
+    graph::draw_map(out, map.heights.width(), map.heights.height(), |offset| {
+        let c = (map.heights.as_slice()[offset] + b'a') as char;
+        let (x, y) = map.heights.pos(offset);
+        let p = Point { x, y };
+        if p == map.start() {
             ('S', graph::COLOR_RED)
-        } else if offset == e.y * map.width + e.x {
+        } else if p == map.end() {
             ('E', graph::COLOR_RED)
         } else {
             (c, graph::SHADE)
@@ -253,19 +225,17 @@ fn draw_map(map: &Map) -> io::Result<()> {
     })
 }
 
-fn draw_path(map: &Map, path: &[Point], modifier: graph::Modifier, slow: bool) -> io::Result<()> {
-    graph::draw_path(map.width, path.iter().map(|p| (p.x, p.y)), move |offset| {
-        if slow {
-            thread::sleep(time::Duration::from_millis(10));
-        }
-        let c = (map.data[offset] + b'a') as char;
+fn draw_path(out: &mut dyn Renderer, map: &Map, path: &[Point], modifier: graph::Modifier, slow: bool) -> io::Result<()> {
+    graph::draw_path(out, map.heights.width(), path.iter().map(|p| (p.x, p.y)), move |offset| {
+        let c = (map.heights.as_slice()[offset] + b'a') as char;
         (c, modifier, slow)
     })
 }
 
 #[cfg(test)]
 mod test {
-    use super::main;
+    use super::{benchmark, part1, part2, visualize, Map};
+    use crate::graph::{self, Frame};
 
     static DATA: &str = r#"Sabqponm
 abcryxxl
@@ -275,7 +245,45 @@ abdefghi"#;
 
     #[test]
     fn solution() {
-        let res = main(DATA).expect("invalid input");
-        assert_eq!(res, (31, 29));
+        let map: Map = DATA.parse().expect("invalid input");
+        assert_eq!(part1(&map).expect("invalid input"), 31);
+        assert_eq!(part2(&map).expect("invalid input"), 29);
+    }
+
+    #[test]
+    fn searches() {
+        let map: Map = DATA.parse().expect("invalid input");
+        let results = benchmark(&map, 2).expect("all searches agree");
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|(_, stats)| stats.samples == 2));
+
+        // the closest 'a' is one step closer than the start
+        let map: Map = format!("abcdefghijklmnopqrstuvwxyzE\nS{}", "z".repeat(26)).parse().expect("invalid input");
+        assert_eq!(part1(&map).expect("invalid input"), 27);
+        assert_eq!(part2(&map).expect("invalid input"), 26);
+    }
+
+    #[test]
+    fn drawing() {
+        let map: Map = DATA.parse().expect("invalid input");
+        let mut frame = Frame::new();
+        visualize(&map, &mut frame).expect("drawn");
+        // the last drawing has the A* path over the BFS one, from S to E
+        assert_eq!(frame.to_string().lines().count(), 5);
+        assert_eq!(frame.get(0, 0), Some(('a', graph::COLOR_GREEN)));
+        assert_eq!(frame.get(5, 2), Some(('z', graph::COLOR_GREEN)));
+        let green = (0..8).flat_map(|x| (0..5).map(move |y| (x, y)))
+            .filter(|&(x, y)| frame.get(x, y).is_some_and(|(_, m)| m == graph::COLOR_GREEN))
+            .count();
+        assert_eq!(green, 32);
+        let text = frame.text();
+        assert_eq!(text[0], "Done");
+        assert_eq!(text.len(), 4);
+        assert!(text[1].starts_with("BFS (RED)") && text[3].starts_with("A* (GREEN)"), "{:?}", text);
+    }
+
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
     }
 }
@@ -1,38 +1,78 @@
 use std::str::Chars;
 use std::cmp::Ordering;
+use std::fmt;
 use anyhow::anyhow as err;
 
-pub fn main(data: &str) -> anyhow::Result<(usize, usize)> {
-    let mut part1 = 0;
-    let mut part2_data = Vec::new();
-    for (idx, pair) in data.trim().split("\n\n").enumerate() {
-        let (left, right) = pair.split_once("\n").ok_or_else(|| err!("expected a pair"))?;
-        let l: Vec<Packet> = <Chars<'_> as Into<Tokenizer<'_>>>::into(left.chars()).collect();
-        let r: Vec<Packet> = <Chars<'_> as Into<Tokenizer<'_>>>::into(right.chars()).collect();
-        if l <= r {
-            part1 += idx + 1;
-        }
-        part2_data.push(l);
-        part2_data.push(r);
-    }
+use crate::parser::ParseError;
+use crate::solution::{Answer, Solution};
+
+pub fn parse(data: &str) -> anyhow::Result<Vec<(Vec<Packet>, Vec<Packet>)>> {
+    // number of the line starting at `at`, a slice of `data`
+    let lineno = |at: &str| {
+        let offset = at.as_ptr() as usize - data.as_ptr() as usize;
+        data[..offset].matches('\n').count() + 1
+    };
+    data.trim()
+        .split("\n\n")
+        .map(|pair| {
+            let (left, right) = pair.split_once('\n')
+                .ok_or_else(|| ParseError::at_end(pair, "expected a pair").line(lineno(pair)))?;
+            let l = packets(left).map_err(|e| e.line(lineno(left)))?;
+            let r = packets(right).map_err(|e| e.line(lineno(right)))?;
+            Ok((l, r))
+        })
+        .collect()
+}
+
+pub fn part1(pairs: &[(Vec<Packet>, Vec<Packet>)]) -> anyhow::Result<usize> {
+    Ok(pairs
+        .iter()
+        .enumerate()
+        .filter(|(_, (l, r))| l <= r)
+        .map(|(idx, _)| idx + 1)
+        .sum())
+}
+
+pub fn part2(pairs: &[(Vec<Packet>, Vec<Packet>)]) -> anyhow::Result<usize> {
+    let mut packets: Vec<_> = pairs
+        .iter()
+        .flat_map(|(l, r)| [l, r])
+        .cloned()
+        .collect();
     let marker_a = vec![Packet::List(vec![Packet::Value(2)])];
     let marker_b = vec![Packet::List(vec![Packet::Value(6)])];
-    part2_data.push(marker_a.clone());
-    part2_data.push(marker_b.clone());
+    packets.push(marker_a.clone());
+    packets.push(marker_b.clone());
 
-    part2_data.sort();
-    let part2 = part2_data
+    packets.sort();
+    let a = packets
         .iter()
         .position(|x| *x == marker_a)
         .ok_or_else(|| err!("marker not found"))? + 1;
-    let part2 = part2 * (part2_data
+    let b = packets
         .iter()
         .position(|x| *x == marker_b)
-        .ok_or_else(|| err!("marker not found"))? + 1);
-    Ok((part1, part2))
+        .ok_or_else(|| err!("marker not found"))? + 1;
+    Ok(a * b)
 }
 
-fn lists_compare<'a, I1, I2>(left: I1, right: I2) -> Option<Ordering>
+pub struct Solver;
+
+impl Solution for Solver {
+    type Input = Vec<(Vec<Packet>, Vec<Packet>)>;
+
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Ordered pairs indices sum", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Decoder key", part2(input)?))
+    }
+}
+
+fn lists_compare<'a, I1, I2>(left: I1, right: I2) -> Ordering
 where
     I1: IntoIterator<Item=&'a Packet>,
     I2: IntoIterator<Item=&'a Packet>,
@@ -41,12 +81,12 @@ where
     let mut r = right.into_iter();
     loop {
         match (l.next(), r.next()) {
-            (None, None) => return Some(Ordering::Equal),
-            (Some(_), None) => return Some(Ordering::Greater),
-            (None, Some(_)) => return Some(Ordering::Less),
+            (None, None) => return Ordering::Equal,
+            (Some(_), None) => return Ordering::Greater,
+            (None, Some(_)) => return Ordering::Less,
             (Some(a), Some(b))=> {
-                match a.partial_cmp(b)? {
-                    o @ (Ordering::Less | Ordering::Greater) => return Some(o),
+                match a.cmp(b) {
+                    o @ (Ordering::Less | Ordering::Greater) => return o,
                     Ordering::Equal => continue
                 }
             }
@@ -54,12 +94,12 @@ where
     }
 }
 
-impl PartialOrd for Packet {
-    fn partial_cmp(&self, other: &Packet) -> Option<Ordering> {
+impl Ord for Packet {
+    fn cmp(&self, other: &Packet) -> Ordering {
         use Packet::*;
 
         match (self, other) {
-            (Value(l), Value(r)) => l.partial_cmp(r),
+            (Value(l), Value(r)) => l.cmp(r),
             (List(l), List(r)) => lists_compare(l, r),
             (lv @ Value(_), List(r)) => lists_compare([lv], r),
             (List(l), rv @ Value(_)) => lists_compare(l, [rv]),
@@ -67,37 +107,70 @@ impl PartialOrd for Packet {
     }
 }
 
+impl PartialOrd for Packet {
+    fn partial_cmp(&self, other: &Packet) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
-#[derive(Debug, Clone, PartialEq, Eq, Ord)]
-enum Packet {
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Packet {
     Value(u32),
     List(Vec<Packet>),
 }
 
-impl FromIterator<Token> for Vec<Packet> {
-    fn from_iter<T>(iter: T) -> Self
-    where
-        T: IntoIterator<Item=Token>
-    {
-        let mut result = Vec::new();
-        let mut it = iter.into_iter();
-        let mut stack = Vec::new();
-        while let Some(token) = it.next() {
-            match token {
-                Token::Value(v) => result.push(Packet::Value(v)),
-                Token::ListStart => {
-                    stack.push(result);
-                    result = Vec::new();
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Packet::Value(v) => write!(f, "{}", v),
+            Packet::List(items) => {
+                write!(f, "[")?;
+                for (idx, item) in items.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
                 }
-                Token::ListEnd => {
-                    let mut tmp = stack.pop().expect("invalid lists stack");
-                    tmp.push(Packet::List(result));
-                    result = tmp;
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+/// Deepest nesting of lists accepted, comparing and dropping packets recurse once per level.
+const MAX_DEPTH: usize = 256;
+
+/// Parses the packets of a single line.
+fn packets(line: &str) -> Result<Vec<Packet>, ParseError> {
+    let mut result = Vec::new();
+    let mut stack = Vec::new();
+    let mut tokens = Tokenizer::new(line);
+    while let Some(token) = tokens.next() {
+        match token? {
+            Token::Value(v) => result.push(Packet::Value(v)),
+            Token::ListStart => {
+                if stack.len() == MAX_DEPTH {
+                    let start = line.len() - tokens.rest().len() - 1;
+                    let message = format!("lists nested more than {} deep", MAX_DEPTH);
+                    return Err(ParseError::at(line, &line[start..], message))
                 }
+                stack.push(result);
+                result = Vec::new();
+            }
+            Token::ListEnd => {
+                let end = line.len() - tokens.rest().len() - 1;
+                let mut tmp = stack.pop()
+                    .ok_or_else(|| ParseError::at(line, &line[end..], "unexpected ']'"))?;
+                tmp.push(Packet::List(result));
+                result = tmp;
             }
         }
-        result
     }
+    if !stack.is_empty() {
+        return Err(ParseError::at_end(line, "expected ']'"));
+    }
+    Ok(result)
 }
 
 // Parsing stuff...
@@ -109,39 +182,45 @@ enum Token {
     ListEnd,
 }
 struct Tokenizer<'a> {
+    line: &'a str,
     chars: Chars<'a>
 }
 
-impl<'a> From<Chars<'a>> for Tokenizer<'a> {
-    fn from(chars: Chars<'a>) -> Tokenizer<'a> {
-        Tokenizer { chars }
+impl<'a> Tokenizer<'a> {
+    fn new(line: &'a str) -> Tokenizer<'a> {
+        Tokenizer { line, chars: line.chars() }
+    }
+
+    /// Input not tokenized yet.
+    fn rest(&self) -> &'a str {
+        self.chars.as_str()
     }
 }
 
 impl<'a> Iterator for Tokenizer<'a> {
-    type Item = Token;
+    type Item = Result<Token, ParseError>;
     fn next(&mut self) -> Option<Self::Item> {
         loop {
+            let at = self.rest();
             let c = self.chars.next()?;
             match c {
-                '[' => return Some(Token::ListStart),
-                ']' => return Some(Token::ListEnd),
+                '[' => return Some(Ok(Token::ListStart)),
+                ']' => return Some(Ok(Token::ListEnd)),
                 ',' => continue,
                 '0'..='9' => {
                     let mut v: u32 = (c as u8 - b'0') as u32;
                     let mut x = self.chars.clone();
-                    loop {
-                        match x.next() {
-                            Some(c @ '0'..='9') => {
-                                v = v * 10 + (c as u8 - b'0') as u32;
-                                self.chars.next();
-                            }
-                            _ => break
+                    while let Some(c @ '0'..='9') = x.next() {
+                        let next = v.checked_mul(10).and_then(|v| v.checked_add((c as u8 - b'0') as u32));
+                        match next {
+                            Some(n) => v = n,
+                            None => return Some(Err(ParseError::at(self.line, at, "number too large"))),
                         }
+                        self.chars.next();
                     }
-                    return Some(Token::Value(v))
+                    return Some(Ok(Token::Value(v)))
                 }
-                _ => unreachable!("Unexpected char {}", c),
+                _ => return Some(Err(ParseError::at(self.line, at, "expected '[', ']', ',' or a number"))),
             }
         }
     }
@@ -149,7 +228,9 @@ impl<'a> Iterator for Tokenizer<'a> {
 
 #[cfg(test)]
 mod test {
-    use super::main;
+    use super::{packets, parse, part1, part2, Packet, MAX_DEPTH};
+    use crate::fuzz::{self, Rng};
+    use crate::parser::ParseError;
 
     static DATA: &str = r#"[1,1,3,1,1]
 [1,1,5,1,1]
@@ -177,7 +258,39 @@ mod test {
 
     #[test]
     fn solution() {
-        let res = main(DATA).expect("invalid input");
-        assert_eq!(res, (13, 140));
+        let pairs = parse(DATA).expect("invalid input");
+        assert_eq!(part1(&pairs).expect("invalid input"), 13);
+        assert_eq!(part2(&pairs).expect("invalid input"), 140);
+    }
+
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
+
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(packets(&nested(MAX_DEPTH)).is_ok());
+        let err = parse(&format!("[]\n{}", nested(100_000))).expect_err("too deep");
+        assert!(err.to_string().starts_with("2:257: lists nested more than 256 deep"), "{}", err);
+    }
+
+    /// Random packet nested at most `depth` lists deep.
+    fn packet(rng: &mut Rng, depth: usize) -> Packet {
+        if depth == 0 || rng.below(3) == 0 {
+            Packet::Value(rng.next() as u32 >> rng.below(32))
+        } else {
+            Packet::List((0..rng.below(5)).map(|_| packet(rng, depth - 1)).collect())
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let generate = |rng: &mut Rng| {
+            let items = (0..rng.below(5)).map(|_| packet(rng, 4)).collect();
+            Packet::List(items).to_string()
+        };
+        fuzz::round_trip(13, generate, |line| {
+            let printed: Vec<_> = packets(line)?.iter().map(Packet::to_string).collect();
+            Ok::<_, ParseError>(printed.join(","))
+        });
     }
 }
@@ -1,233 +1,186 @@
-use anyhow::anyhow as err;
-use std::convert::TryFrom;
 use std::cmp;
-
-use crate::graph;
-
-
-pub fn main(data: &str) -> anyhow::Result<(usize, usize)> {
-    let mut bounding_box: [isize; 4] = [500, 0, 500, 0];
-    let mut tmp = Vec::new();
-    for line in data.lines() {
-        let coords: Vec<_> = line.split(" -> ").collect();
-        for start_end in coords.windows(2).map(|slice| (slice[0], slice[1])) {
-            let wall: Wall = start_end.try_into()?;
-            bounding_box = [
-                cmp::min(bounding_box[0], wall.x0),
-                cmp::min(bounding_box[1], wall.y0),
-                cmp::max(bounding_box[2], wall.x1),
-                cmp::max(bounding_box[3], wall.y1),
-            ];
-            tmp.push(wall);
-        }
-    }
-    bounding_box = [
-        bounding_box[0] - 1,
-        cmp::max(bounding_box[1] - 1, 0),
-        bounding_box[2] + 2,
-        bounding_box[3] + 2,
-    ];
-    let mut bounding_box2 = bounding_box;
-
-    let mut map = Map::new(bounding_box);
-    map.extend(&tmp);
-
-    let w = (map.x1 - map.x0) as usize;
-    let h = (map.y1 - map.y0) as usize;
-    graph::draw_map(w, h, |offset: usize| {
-        if map.buf[offset] == 0 {
-            ('.', graph::SHADE)
-        } else if map.buf[offset] == 1 {
-            ('#', graph::COLOR_BLUE)
-        } else {
-            ('o', graph::HIGHLIGHT)
-        }
-    })?;
-    let mut part1 = 0;
-    loop {
-        let start = Point { x: 500, y: 0 };
-        match map.trace(start) {
-            Some(stop) => {
-                map.insert(&stop);
-                graph::delay_draw_char(
-                    (stop.x - map.x0) as usize,
-                    stop.y as usize,
-                    ('o', graph::HIGHLIGHT),
-                )?;
+use std::io;
+
+use crate::graph::{self, Renderer};
+use crate::grid::{Grid, SparseGrid};
+use crate::parser::{self, ParseError};
+use crate::solution::{Answer, Solution};
+
+/// Largest coordinate accepted, keeps the maps small.
+const MAX_COORD: i64 = 1000;
+
+pub fn parse(data: &str) -> anyhow::Result<Vec<Wall>> {
+    let paths = parser::lines(data, |line| {
+        let mut walls = Vec::new();
+        let mut prev = None;
+        for at in line.split(" -> ") {
+            let p = point(line, at)?;
+            if let Some(q) = prev {
+                let wall = Wall::new(q, p)
+                    .ok_or_else(|| ParseError::at(line, at, "expected a horizontal or vertical wall"))?;
+                walls.push(wall);
             }
-            None => break
-        }
-        part1 += 1;
-    }
-    bounding_box2[3] += 1;
-    bounding_box2[0] = 500 - bounding_box2[3];
-    bounding_box2[2] = 500 + bounding_box2[3];
-    let mut map = Map::new(bounding_box2);
-    map.extend(&tmp);
-    // Add floor
-    map.extend(&[Wall {
-        x0: bounding_box2[0],
-        y0: bounding_box2[3] - 1,
-        x1: bounding_box2[2] - 1,
-        y1: bounding_box2[3] - 1,
-    }]);
-
-    let w = (map.x1 - map.x0) as usize;
-    let h = (map.y1 - map.y0) as usize;
-    graph::draw_map(w, h, |offset: usize| {
-        if map.buf[offset] == 0 {
-            ('.', graph::SHADE)
-        } else if map.buf[offset] == 1 {
-            ('#', graph::COLOR_BLUE)
-        } else {
-            ('o', graph::HIGHLIGHT)
+            prev = Some(p);
         }
+        Ok(walls)
     })?;
-    let mut part2 = 0;
-    loop {
-        let start = Point { x: 500, y: 0 };
-        match map.trace(start) {
-            Some(stop) => {
-                map.insert(&stop);
-                graph::delay_draw_char(
-                    (stop.x - map.x0) as usize,
-                    stop.y as usize,
-                    ( 'o', graph::HIGHLIGHT),
-                )?;
-            }
-            None => break
+    Ok(paths.into_iter().flatten().collect())
+}
+
+/// Parses `x,y` coordinates at `at`, a slice of `line`.
+fn point(line: &str, at: &str) -> Result<(i64, i64), ParseError> {
+    let (x, y) = parser::split(line, at, ",")?;
+    let coord = |at| match parser::number(line, at)? {
+        c @ 0..=MAX_COORD => Ok(c),
+        _ => Err(ParseError::at(line, at, format!("expected a coordinate from 0 to {}", MAX_COORD))),
+    };
+    Ok((coord(x)?, coord(y)?))
+}
+
+pub fn part1(walls: &[Wall]) -> anyhow::Result<usize> {
+    Ok(pour_sand(&mut Map::new(walls, false), |_| ()))
+}
+
+pub fn part2(walls: &[Wall]) -> anyhow::Result<usize> {
+    Ok(pour_sand(&mut Map::new(walls, true), |_| ()))
+}
+
+/// Animates sand falling, first into the abyss then onto the floor.
+pub fn visualize(walls: &[Wall], out: &mut dyn Renderer) -> anyhow::Result<()> {
+    for floor in [false, true] {
+        // pour first to know the window the sand covers
+        let mut map = Map::new(walls, floor);
+        let mut stops = Vec::new();
+        pour_sand(&mut map, |stop| stops.push(stop));
+        let Some(bounds) = map.cells.bounds() else {
+            continue
+        };
+        let mut window = map.cells.window(|cell| cell.copied().filter(|&c| c == Cell::Rock));
+        if floor {
+            window.resize_rows(window.height() + 1, Some(Cell::Rock));
+        }
+        draw_map(out, &window)?;
+        for (x, y) in stops {
+            let (x, y) = ((x - bounds.min.0) as usize, (y - bounds.min.1) as usize);
+            graph::delay_draw_char(out, x, y, ('o', graph::HIGHLIGHT))?;
         }
-        part2 += 1;
+        graph::goto_line(out, window.height())?;
     }
-    graph::goto_line(h)?;
-    Ok((part1, part2))
+    Ok(())
 }
 
-#[derive(Debug)]
-struct Point {
-    x: isize,
-    y: isize,
+pub struct Solver;
+
+impl Solution for Solver {
+    type Input = Vec<Wall>;
+
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Resting sand units", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Resting sand units with floor", part2(input)?))
+    }
+    fn visualize(input: &Self::Input, out: &mut dyn Renderer) -> anyhow::Result<()> {
+        visualize(input, out)
+    }
 }
 
-#[derive(Debug, Default)]
+/// Where the sand comes from.
+const SOURCE: (i64, i64) = (500, 0);
+
+/// Drops sand units until they stop coming to rest, returns number of rested units.
+fn pour_sand(map: &mut Map, mut on_rest: impl FnMut((i64, i64))) -> usize {
+    let mut count = 0;
+    let mut path = Vec::new();
+    while let Some(stop) = map.trace(&mut path) {
+        map.cells.insert(stop, Cell::Sand);
+        on_rest(stop);
+        count += 1;
+    }
+    count
+}
+
+fn draw_map(out: &mut dyn Renderer, window: &Grid<Option<Cell>>) -> io::Result<()> {
+    graph::draw_map(out, window.width(), window.height(), |offset: usize| {
+        match window.as_slice()[offset] {
+            None => ('.', graph::SHADE),
+            Some(Cell::Rock) => ('#', graph::COLOR_BLUE),
+            Some(Cell::Sand) => ('o', graph::HIGHLIGHT),
+        }
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Rock,
+    Sand,
+}
+
+/// Rocks and resting sand, every other cell is air.
+#[derive(Debug)]
 struct Map {
-    x0: isize,
-    y0: isize,
-    x1: isize,
-    y1: isize,
-    buf: Vec<u8>,
+    cells: SparseGrid<Cell>,
+    /// Row of the lowest wall.
+    lowest: i64,
+    /// Whether there is an infinite floor two rows below the lowest wall, or an endless void.
+    floor: bool,
 }
 
 impl Map {
-    fn new(bounding_box: [isize; 4]) -> Self {
-        let dx = (bounding_box[2] - bounding_box[0]) as usize;
-        let dy = (bounding_box[3] - bounding_box[1]) as usize;
-        let buf = [0].repeat(dx * dy);
-        Map {
-            x0: bounding_box[0],
-            y0: bounding_box[1],
-            x1: bounding_box[2],
-            y1: bounding_box[3],
-            buf,
-        }
-    }
-    fn offset(&self, x: isize, y: isize) -> usize {
-        let x = x - self.x0;
-        let y = y - self.y0;
-        let offset = y * (self.x1 - self.x0) + x;
-        offset.try_into().expect("positive integer")
-    }
-    fn extend(&mut self, walls: &[Wall]) {
+    fn new(walls: &[Wall], floor: bool) -> Self {
+        let mut cells = SparseGrid::new();
         for w in walls {
-            if w.is_horizontal() {
-                for x in w.x0..=w.x1 {
-                    let offset = self.offset(x, w.y0);
-                    self.buf[offset] = 1;
-                }
-            } else {
-                assert!(w.is_vertical(), "expected vertical: {:?}", w);
+            for x in w.x0..=w.x1 {
                 for y in w.y0..=w.y1 {
-                    let offset = self.offset(w.x0, y);
-                    self.buf[offset] = 1;
+                    cells.insert((x, y), Cell::Rock);
                 }
             }
         }
+        let lowest = walls.iter().map(|w| w.y1).max().unwrap_or(0);
+        Map { cells, lowest, floor }
     }
-    fn insert(&mut self, point: &Point) {
-        let offset = self.offset(point.x, point.y);
-        self.buf[offset] = 2;
+
+    fn is_air(&self, (x, y): (i64, i64)) -> bool {
+        !(self.cells.contains((x, y)) || self.floor && y == self.lowest + 2)
     }
-    fn trace(&self, start: Point) -> Option<Point> {
-        let mut todo = vec![start];
+
+    /// Where the next unit of sand comes to rest, `None` if it falls into the void
+    /// or the source is blocked.
+    ///
+    /// `path` is the way down of the previous unit, the next one follows it up to
+    /// where the previous one rested.
+    fn trace(&self, path: &mut Vec<(i64, i64)>) -> Option<(i64, i64)> {
+        if path.is_empty() && self.is_air(SOURCE) {
+            path.push(SOURCE);
+        }
         loop {
-            let p = todo.remove(0);
-            let y = (p.y..self.y1)
-                .into_iter()
-                .take_while(|y| {
-                    let offset = self.offset(p.x, *y);
-                    self.buf[offset] == 0
-                }).last();
-            match y {
-                None => return None,
-                Some(y) if y == self.y1 - 1 => return None,
-                Some(y) => {
-                    let stop = Point { x: p.x, y };
-                    if let Some(left) = self.slide_left(&stop) {
-                        todo.push(left);
-                    } else if let Some(right) = self.slide_right(&stop) {
-                        todo.push(right);
-                    } else {
-                        return Some(stop)
-                    }
-                }
+            let &(x, y) = path.last()?;
+            if !self.floor && y > self.lowest {
+                return None
+            }
+            match [x, x - 1, x + 1].into_iter().find(|&x| self.is_air((x, y + 1))) {
+                Some(x) => path.push((x, y + 1)),
+                None => return path.pop(),
             }
         }
     }
-    fn slide_left(&self, p: &Point) -> Option<Point> {
-        let offset = self.offset(p.x - 1, p.y + 1);
-        (self.buf[offset] == 0).then(|| Point {
-            x: p.x - 1,
-            y: p.y + 1,
-        })
-    }
-    fn slide_right(&self, p: &Point) -> Option<Point> {
-        let offset = self.offset(p.x + 1, p.y + 1);
-        (self.buf[offset] == 0).then(|| Point {
-            x: p.x + 1,
-            y: p.y + 1,
-        })
-
-    }
 }
 
 #[derive(Debug, Clone)]
-struct Wall {
-    x0: isize,
-    y0: isize,
-    x1: isize,
-    y1: isize,
+pub struct Wall {
+    x0: i64,
+    y0: i64,
+    x1: i64,
+    y1: i64,
 }
 
 impl Wall {
-    fn is_horizontal(&self) -> bool {
-        self.y0 == self.y1
-    }
-    fn is_vertical(&self) -> bool {
-        self.x0 == self.x1
-    }
-}
-
-impl TryFrom<(&str, &str)> for Wall {
-    type Error = anyhow::Error;
-
-    fn try_from((start, end): (&str, &str)) -> anyhow::Result<Self> {
-        let (x0, y0) = start.split_once(",").ok_or_else(|| err!("comma expected"))?;
-        let x0: isize = x0.parse()?;
-        let y0: isize = y0.parse()?;
-        let (x1, y1) = end.split_once(",").ok_or_else(|| err!("comma expected"))?;
-        let x1: isize = x1.parse()?;
-        let y1: isize = y1.parse()?;
-        Ok(Wall {
+    /// Wall between two points, `None` if it is diagonal.
+    fn new((x0, y0): (i64, i64), (x1, y1): (i64, i64)) -> Option<Self> {
+        (x0 == x1 || y0 == y1).then(|| Wall {
             x0: cmp::min(x0, x1),
             x1: cmp::max(x0, x1),
             y0: cmp::min(y0, y1),
@@ -238,7 +191,8 @@ impl TryFrom<(&str, &str)> for Wall {
 
 #[cfg(test)]
 mod test {
-    use super::main;
+    use super::{parse, part1, part2, visualize};
+    use crate::graph::Frame;
 
     static DATA: &str = r#"
 498,4 -> 498,6 -> 496,6
@@ -247,7 +201,25 @@ mod test {
 
     #[test]
     fn solution() {
-        let res = main(DATA.trim()).expect("invalid input");
-        assert_eq!(res, (93, 0));
+        let walls = parse(DATA.trim()).expect("invalid input");
+        assert_eq!(part1(&walls).expect("invalid input"), 24);
+        assert_eq!(part2(&walls).expect("invalid input"), 93);
+    }
+
+    #[test]
+    fn drawing() {
+        let walls = parse(DATA.trim()).expect("invalid input");
+        let mut frame = Frame::new();
+        visualize(&walls, &mut frame).expect("drawn");
+        // the last drawing is the sand on the floor
+        let drawing = frame.to_string();
+        assert_eq!(drawing.matches('o').count(), 93);
+        assert!(drawing.lines().last().is_some_and(|floor| floor.chars().all(|c| c == '#')), "{}", drawing);
+        assert_eq!(drawing.lines().next(), Some("..........o.........."));
+    }
+
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
     }
 }
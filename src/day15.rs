@@ -1,79 +1,110 @@
-use anyhow::anyhow as err;
 use std::collections::HashSet;
 use std::cmp;
-use rayon::prelude::*;
+use anyhow::anyhow as err;
 
 use crate::day4::Range;
+use crate::parser::{self, ParseError};
+use crate::solution::{Answer, Solution};
 
-#[cfg(test)]
-const PART1_ROW: i32 = 10;
-#[cfg(test)]
-const N_COLS: i32 = 20;
-
-#[cfg(not(test))]
-const PART1_ROW: i32 = 2_000_000;
-#[cfg(not(test))]
-const N_COLS: i32 = 4_000_000;
-
-pub fn main(data: &str) -> anyhow::Result<(usize, u64)> {
-    let mut sensors = Vec::with_capacity(data.lines().count());
-    let mut beacons = Vec::with_capacity(data.lines().count());
-    for line in data.lines() {
-        if !line.starts_with("Sensor at x=") {
-            return Err(err!("invalid line start"))
-        }
+/// Row scanned in part 1 of the puzzle.
+pub const PART1_ROW: i32 = 2_000_000;
+/// Largest coordinate of the distress beacon in part 2 of the puzzle.
+pub const SEARCH_MAX: i32 = 4_000_000;
 
-        let (part1, part2) = line["Sensor at x=".len()..]
-            .split_once(": closest beacon is at x=")
-            .ok_or_else(|| err!("invalid line"))?;
-        let (x1, y1) = part1.split_once(", y=").ok_or_else(|| err!(" y= expected"))?;
-        let (x2, y2) = part2.split_once(", y=").ok_or_else(|| err!(" y= expected"))?;
-        let x1: i32 = x1.parse()?;
-        let y1: i32 = y1.parse()?;
-        let x2: i32 = x2.parse()?;
-        let y2: i32 = y2.parse()?;
-        beacons.push((x2, y2));
-        sensors.push(Sensor {
-            x: x1,
-            y: y1,
-            r: (x2 - x1).abs() + (y2 - y1).abs(),
-        });
-    }
+/// Row scanned in part 1 of the example.
+pub const EXAMPLE_ROW: i32 = 10;
+/// Largest coordinate of the distress beacon in part 2 of the example,
+/// and of every sensor of the readings taken for the example.
+pub const EXAMPLE_MAX: i32 = 20;
+
+const TUNING_MULTIPLIER: i64 = 4_000_000;
 
-    let mut lines: HashSet<_> = sensors
+pub fn parse(data: &str) -> anyhow::Result<Readings> {
+    let readings = parser::lines(data, |line| {
+        let rest = parser::prefix(line, line, "Sensor at x=")?;
+        let (part1, part2) = parser::split(line, rest, ": closest beacon is at x=")?;
+        let (x1, y1) = parser::split(line, part1, ", y=")?;
+        let (x2, y2) = parser::split(line, part2, ", y=")?;
+        let number = |at| parser::number::<i32>(line, at);
+        let (x, y) = (number(x1)?, number(y1)?);
+        let beacon = (number(x2)?, number(y2)?);
+        // the whole covered area must stay within i32
+        let r = distance(x, beacon.0)
+            .zip(distance(y, beacon.1))
+            .and_then(|(dx, dy)| dx.checked_add(dy))
+            .filter(|&r| [x, y].iter().all(|c| c.checked_sub(r).and(c.checked_add(r)).is_some()))
+            .ok_or_else(|| ParseError::at(line, line, "coordinates out of range"))?;
+        Ok((Sensor { x, y, r }, beacon))
+    })?;
+    let (sensors, beacons) = readings.into_iter().unzip();
+    Ok(Readings { sensors, beacons })
+}
+
+/// Number of positions in `row` where no beacon can be.
+pub fn part1(readings: &Readings, row: i32) -> anyhow::Result<usize> {
+    let covered = readings.covered(row);
+    let beacons: HashSet<_> = readings.beacons
         .iter()
-        .filter_map(|s| s.intersects_horizontal(PART1_ROW))
-        .flat_map(|(s, e)| s..=e)
+        .filter(|&&(x, y)| y == row && covered.iter().any(|r| r.start <= x as i64 && x as i64 <= r.end))
         .collect();
-    for (x, y) in beacons {
-        if y == PART1_ROW && lines.contains(&x) {
-            lines.remove(&x);
-        }
+    let total: i64 = covered.iter().map(|r| r.end - r.start + 1).sum();
+    Ok(total as usize - beacons.len())
+}
+
+/// Tuning frequency of the only position with both coordinates in `0..=max` not covered
+/// by any sensor.
+///
+/// Such a position lies just outside the ranges of the sensors around it, so it is
+/// an intersection of their diamond edges, or of an edge and the border of the area.
+pub fn part2(readings: &Readings, max: i32) -> anyhow::Result<i64> {
+    let max = max as i64;
+    // edges just outside each sensor range, on lines x + y = a and x - y = b
+    let mut a_lines = Vec::new();
+    let mut b_lines = Vec::new();
+    for s in &readings.sensors {
+        let (x, y, r) = (s.x as i64, s.y as i64, s.r as i64 + 1);
+        a_lines.extend([x + y - r, x + y + r]);
+        b_lines.extend([x - y - r, x - y + r]);
     }
-    let part1 = lines.len();
-
-    let mut part2 = 0;
-    let test_range = (0i32, N_COLS).into();
-    for i in 0..N_COLS {
-        // println!("{}", i);
-        let mut v_segments: Vec<Range<_>> = sensors
-            .par_iter()
-            .filter_map(|s| s.intersects_vertical(i).map(|s| s.into()))
-            .collect();
-        sort_and_dedup_ranges(&mut v_segments);
-        // skip ranges that cover searched range
-        if v_segments[0].contains(&test_range) {
-            continue
-        }
-        if let Some(y) = (0..N_COLS)
-            .into_par_iter()
-            .find_first(|y| v_segments.iter().all(|s| !s.contains_point(&y)))
-        {
-            part2 = i as u64 * 4_000_000u64 + y as u64;
-        }
+    let (a_lines, b_lines) = (&a_lines, &b_lines);
+    let crossings = a_lines.iter().flat_map(|&a| b_lines.iter().filter_map(move |&b| {
+        ((a + b) % 2 == 0).then_some(((a + b) / 2, (a - b) / 2))
+    }));
+    let border = [0, max];
+    let on_border = border.iter().flat_map(|&k| {
+        a_lines.iter().flat_map(move |&a| [(k, a - k), (a - k, k)])
+            .chain(b_lines.iter().flat_map(move |&b| [(k, k - b), (b + k, k)]))
+    });
+    let corners = border.iter().flat_map(|&x| border.iter().map(move |&y| (x, y)));
+    crossings
+        .chain(on_border)
+        .chain(corners)
+        .find(|&(x, y)| (0..=max).contains(&x) && (0..=max).contains(&y) && !readings.is_covered(x, y))
+        .map(|(x, y)| x * TUNING_MULTIPLIER + y)
+        .ok_or_else(|| err!("every position is covered by a sensor"))
+}
+
+pub struct Solver;
+
+impl Solution for Solver {
+    type Input = Readings;
+
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Positions without beacon", part1(input, input.scan().0)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Tuning frequency", part2(input, input.scan().1)?))
     }
+}
 
-    Ok((part1, part2))
+/// Sensors with their coverage radius and positions of the closest beacons.
+#[derive(Debug)]
+pub struct Readings {
+    sensors: Vec<Sensor>,
+    beacons: Vec<(i32, i32)>,
 }
 
 #[derive(Debug)]
@@ -83,29 +114,56 @@ struct Sensor {
     r: i32,
 }
 
+impl Readings {
+    /// Row scanned in part 1 and largest coordinate of the distress beacon in part 2:
+    /// those of the example when all the sensors are within `0..=EXAMPLE_MAX`,
+    /// those of the puzzle otherwise.
+    pub fn scan(&self) -> (i32, i32) {
+        let example = |c: i32| (0..=EXAMPLE_MAX).contains(&c);
+        if self.sensors.iter().all(|s| example(s.x) && example(s.y)) {
+            (EXAMPLE_ROW, EXAMPLE_MAX)
+        } else {
+            (PART1_ROW, SEARCH_MAX)
+        }
+    }
+
+    /// Merged ranges of columns covered by the sensors in row `y`.
+    fn covered(&self, y: i32) -> Vec<Range<i64>> {
+        let mut ranges: Vec<_> = self.sensors
+            .iter()
+            .filter_map(|s| s.intersects_horizontal(y as i64))
+            .collect();
+        sort_and_dedup_ranges(&mut ranges);
+        ranges
+    }
+
+    fn is_covered(&self, x: i64, y: i64) -> bool {
+        self.sensors.iter().any(|s| s.covers(x, y))
+    }
+}
+
 impl Sensor {
-    fn intersects_horizontal(&self, y: i32) -> Option<(i32, i32)> {
-        (self.y - self.r <= y && y <= self.y + self.r)
-        .then(|| {
-            let dx = (self.r - (y - self.y).abs()).abs();
-            (self.x - dx, self.x + dx)
-        })
+    fn intersects_horizontal(&self, y: i64) -> Option<Range<i64>> {
+        let dx = self.r as i64 - (y - self.y as i64).abs();
+        (dx >= 0).then(|| (self.x as i64 - dx, self.x as i64 + dx).into())
     }
-    fn intersects_vertical(&self, x: i32) -> Option<(i32, i32)> {
-        (self.x - self.r <= x && x <= self.x + self.r)
-        .then(|| {
-            let dy = (self.r - (x - self.x).abs()).abs();
-            (self.y - dy, self.y + dy)
-        })
+
+    fn covers(&self, x: i64, y: i64) -> bool {
+        (x - self.x as i64).abs() + (y - self.y as i64).abs() <= self.r as i64
     }
 }
 
+/// Distance between two coordinates, `None` on overflow.
+fn distance(a: i32, b: i32) -> Option<i32> {
+    a.checked_sub(b).and_then(i32::checked_abs)
+}
+
 fn sort_and_dedup_ranges<T>(vec: &mut Vec<Range<T>>)
 where
     T: Ord + Copy + std::fmt::Debug,
 {
     let mut len = usize::MAX;
-    vec.sort_by(|r1, r2| r1.start.cmp(&r2.start));
+    vec.sort_by_key(|r| r.start);
     loop {
         vec.dedup_by(|r1, r2| {
             if r1.overlaps_inclusive(r2) {
@@ -125,7 +183,8 @@ where
 
 #[cfg(test)]
 mod test {
-    use super::main;
+    use super::{parse, part1, part2, Solver, EXAMPLE_MAX, EXAMPLE_ROW, PART1_ROW, SEARCH_MAX};
+    use crate::solution::Solution;
     static DATA: &str = r#"
 Sensor at x=2, y=18: closest beacon is at x=-2, y=15
 Sensor at x=9, y=16: closest beacon is at x=10, y=16
@@ -145,7 +204,28 @@ Sensor at x=20, y=1: closest beacon is at x=15, y=3
 
     #[test]
     fn solution() {
-        let res = main(DATA.trim()).expect("invalid input");
-        assert_eq!(res, (26, 56000011));
+        let readings = parse(DATA.trim()).expect("invalid input");
+        assert_eq!(part1(&readings, 10).expect("invalid input"), 26);
+        assert_eq!(part2(&readings, 20).expect("invalid input"), 56000011);
+
+        // the example is recognized by the solver
+        assert_eq!(readings.scan(), (EXAMPLE_ROW, EXAMPLE_MAX));
+        assert_eq!(Solver::part1(&readings).expect("invalid input").value, "26");
+        assert_eq!(Solver::part2(&readings).expect("invalid input").value, "56000011");
+        let readings = parse("Sensor at x=21, y=0: closest beacon is at x=0, y=0").expect("invalid input");
+        assert_eq!(readings.scan(), (PART1_ROW, SEARCH_MAX));
+    }
+
+    #[test]
+    fn corner() {
+        // everything but (2, 2) is within the range of the sensor
+        let readings = parse("Sensor at x=0, y=0: closest beacon is at x=3, y=0").expect("invalid input");
+        assert_eq!(part2(&readings, 2).expect("invalid input"), 2 * 4_000_000 + 2);
+        assert!(part2(&readings, 1).is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
     }
 }
@@ -1,68 +1,155 @@
 use std::str;
-use std::collections::{HashMap, HashSet};
 use anyhow::anyhow as err;
 
-use crate::bms;
+use crate::parser::{self, ParseError};
+use crate::labeled::{Distances, Graph};
+use crate::solution::{Answer, Solution};
 
-pub fn main(data: &str) -> anyhow::Result<(usize, usize)> {
-    let mut valves = data
-        .lines()
-        .map(|l| l.parse())
-        .collect::<anyhow::Result<Vec<Valve>>>()?;
-    println!("");
-    let graph = Graph::build(&valves);
+/// Valve where you start.
+const START: &str = "AA";
 
-    Ok((0, 0))
+/// Part 2 keeps the best pressure for every set of opened valves, which limits their number.
+const MAX_USEFUL_VALVES: usize = 20;
+
+pub fn parse(data: &str) -> anyhow::Result<Vec<Valve>> {
+    Ok(parser::lines(data, str::parse)?)
+}
+
+pub fn part1(valves: &[Valve]) -> anyhow::Result<u64> {
+    let network = Network::build(valves)?;
+    let mut best = 0;
+    network.explore(30, &mut |_, released| best = best.max(released));
+    Ok(best)
+}
+
+pub fn part2(valves: &[Valve]) -> anyhow::Result<u64> {
+    let network = Network::build(valves)?;
+    let n = network.rates.len();
+    if n > MAX_USEFUL_VALVES {
+        return Err(err!("too many valves with non-zero flow rate: {} (at most {})", n, MAX_USEFUL_VALVES));
+    }
+    // best[set] is the most pressure released by opening exactly the valves in `set`
+    let mut best = vec![0; 1 << n];
+    network.explore(26, &mut |opened, released| {
+        let best = &mut best[opened as usize];
+        *best = (*best).max(released);
+    });
+    // ... and then by opening any subset of them
+    for valve in 0..n {
+        for set in 0..best.len() {
+            if set & 1 << valve != 0 {
+                best[set] = best[set].max(best[set ^ 1 << valve]);
+            }
+        }
+    }
+    // you and the elephant open disjoint sets of valves
+    let all = best.len() - 1;
+    Ok((0..best.len()).map(|set| best[set] + best[all ^ set]).max().unwrap_or(0))
+}
+
+pub struct Solver;
+
+impl Solution for Solver {
+    type Input = Vec<Valve>;
+
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Max pressure released", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Max pressure released with elephant", part2(input)?))
+    }
 }
 
 #[derive(Debug)]
-struct Valve {
+pub struct Valve {
     label: String,
     rate: u32,
     next: Vec<String>,
 }
 
 impl str::FromStr for Valve {
-    type Err = anyhow::Error;
-    fn from_str(val: &str) -> anyhow::Result<Self> {
-        let mut it = val.split(" ");
-        let label = it.nth(1).ok_or_else(|| err!("label is expected"))?.to_string();
-        let rate_str = it.nth(2).ok_or_else(|| err!("rate is expected"))?;
-        let rate = rate_str[5..rate_str.len() - 1].parse()?;
-        let next = it.skip(4).map(|word| word.trim_end_matches(',').to_string()).collect();
+    type Err = ParseError;
+    fn from_str(val: &str) -> Result<Self, ParseError> {
+        let rest = parser::prefix(val, val, "Valve ")?;
+        let (label, rest) = parser::split(val, rest, " has flow rate=")?;
+        let (rate, rest) = parser::split(val, rest, "; ")?;
+        let rate = parser::number(val, rate)?;
+        let next = rest
+            .strip_prefix("tunnels lead to valves ")
+            .or_else(|| rest.strip_prefix("tunnel leads to valve "))
+            .ok_or_else(|| ParseError::at(val, rest, "expected 'tunnels lead to valves '"))?
+            .split(", ")
+            .map(str::to_string)
+            .collect();
         Ok(Valve {
-            label,
+            label: label.to_string(),
             rate,
             next,
         })
     }
 }
 
+/// Tunnels reduced to the valves worth opening and the minutes needed to walk between them.
 #[derive(Debug)]
-struct Graph {
-    links: HashMap<String, Vec<String>>,
+struct Network {
+    rates: Vec<u64>,
+    /// Minutes between the start (0) and the valves (from 1), `None` if unreachable.
+    dist: Distances,
 }
 
-impl Graph {
-    fn build(valves: &[Valve]) -> Self {
-        let links = valves.iter().map(|v| (v.label.clone(), v.next.clone())).collect();
-        Graph { links }
+impl Network {
+    fn build(valves: &[Valve]) -> anyhow::Result<Self> {
+        let mut graph = Graph::<u16>::new();
+        for valve in valves {
+            graph.add_node(&valve.label)?;
+            for next in &valve.next {
+                graph.add_edge(&valve.label, next)?;
+            }
+        }
+        let start = graph.labels.get(START).ok_or_else(|| err!("no valve {} to start from", START))?;
+        let useful: Vec<_> = valves.iter().filter(|v| v.rate > 0).collect();
+        if useful.len() > 64 {
+            return Err(err!("too many valves with non-zero flow rate: {}", useful.len()));
+        }
+        let mut nodes = vec![start];
+        nodes.extend(useful.iter().filter_map(|v| graph.labels.get(&v.label)));
+        Ok(Network {
+            rates: useful.iter().map(|v| v.rate as u64).collect(),
+            dist: graph.compress(&nodes).distances,
+        })
     }
 
-    fn path(&self, from: &str, to: &str) -> Vec<String> {
-        let path = bms::build_path(from, to, |node| {
-            self.links.get(node).expect("valid node")
-        });
-        path.expect("path exists")
+    /// Calls `visit` with the set of opened valves (as a bit mask) and the pressure released
+    /// in `time` minutes, for every order of opening valves that can be completed in time.
+    fn explore<F: FnMut(u64, u64)>(&self, time: u32, visit: &mut F) {
+        self.explore_from(0, time, 0, 0, visit)
     }
-    fn distance(&self, from: &str, to: &str) -> usize {
-        self.path(from, to).len()
+
+    fn explore_from<F>(&self, from: usize, time: u32, opened: u64, released: u64, visit: &mut F)
+    where
+        F: FnMut(u64, u64),
+    {
+        visit(opened, released);
+        for (next, d) in self.dist.row(from)[1..].iter().enumerate() {
+            match d {
+                // opening takes a minute, and must leave some time for the valve to release pressure
+                Some(d) if opened & 1 << next == 0 && d + 1 < time => {
+                    let left = time - d - 1;
+                    let released = released + left as u64 * self.rates[next];
+                    self.explore_from(next + 1, left, opened | 1 << next, released, visit);
+                }
+                _ => continue,
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::main;
+    use super::{parse, part1, part2};
 
     static DATA: &str = r#"
 Valve AA has flow rate=0; tunnels lead to valves DD, II, BB
@@ -79,8 +166,13 @@ Valve JJ has flow rate=21; tunnel leads to valve II
 
     #[test]
     fn solution() {
-        let res = main(DATA.trim()).expect("invalid input");
-        assert_eq!(res, (1651, 0));
+        let valves = parse(DATA.trim()).expect("invalid input");
+        assert_eq!(part1(&valves).expect("invalid input"), 1651);
+        assert_eq!(part2(&valves).expect("invalid input"), 1707);
+    }
 
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
     }
 }
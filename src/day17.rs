@@ -1,232 +1,256 @@
+use std::collections::HashMap;
+
 use anyhow::anyhow as err;
-use std::time;
+
+use crate::grid::{Grid, Pos};
+use crate::parser::ParseError;
+use crate::solution::{Answer, Solution};
 
 const PART1_SHAPES: usize = 2022;
 const PART2_SHAPES: usize = 1_000_000_000_000;
 
-pub fn main(data: &str) -> anyhow::Result<(usize, usize)> {
-    let moves: Vec<_> = data.trim().chars().map(|c| match c {
+pub fn parse(data: &str) -> anyhow::Result<Vec<Shift>> {
+    let line = data.trim();
+    let moves = line.char_indices().map(|(idx, c)| match c {
         '<' => Ok(Shift::Left),
         '>' => Ok(Shift::Right),
-        _ => Err(err!("invalid character {}", c)),
-    }).collect::<anyhow::Result<Vec<_>>>()?;
-
-    let shapes = vec![
-        Shape::Minus,
-        Shape::Plus,
-        Shape::LShape,
-        Shape::Line,
-        Shape::Square,
-    ];
-
-    println!("Moves: {}", moves.len());
-    println!("Shapes: {}", shapes.len());
-
-    let mut moves_iter = moves.iter().cycle();
-    let mut field1 = Field::new();
-    for shape in shapes.iter().cycle().take(PART1_SHAPES) {
-        for _ in 0..3 {
-            let shift = moves_iter.next().expect("corrupted cycle iterator");
-            field1.shift_to_unchecked(*shift, &shape);
+        _ => Err(ParseError::at(line, &line[idx..], "expected jet '<' or '>'").line(1)),
+    }).collect::<Result<_, _>>()?;
+    Ok(moves)
+}
+
+pub fn part1(moves: &[Shift]) -> anyhow::Result<usize> {
+    height_after(moves, PART1_SHAPES)
+}
+
+pub fn part2(moves: &[Shift]) -> anyhow::Result<usize> {
+    height_after(moves, PART2_SHAPES)
+}
+
+/// Height of the tower after `n_rocks` rocks fell.
+///
+/// Once the next shape, the next jet and the top of the tower repeat, so does the growth
+/// of the tower, which lets whole cycles of rocks be skipped. The top of the tower is only
+/// its last `SURFACE_ROWS` rows, so a cycle is only trusted once the tower grew the same,
+/// rock by rock, over two periods in a row.
+pub fn height_after(moves: &[Shift], n_rocks: usize) -> anyhow::Result<usize> {
+    if moves.is_empty() {
+        return Err(err!("no jets of hot gas"));
+    }
+    let mut tower = Tower::new(moves);
+    // heights[n] is the height after n rocks
+    let mut heights = vec![0];
+    let mut seen = HashMap::new();
+    while tower.rocks < n_rocks {
+        tower.drop_rock();
+        heights.push(tower.height());
+        let Some(start) = seen.insert(tower.state(), tower.rocks) else {
+            continue
+        };
+        let period = tower.rocks - start;
+        let repeats = start >= period && (0..=period)
+            .all(|i| heights[start + i] - heights[start] == heights[start - period + i] - heights[start - period]);
+        if !repeats {
+            continue
         }
+        let growth = tower.height() - heights[start];
+        let left = n_rocks - tower.rocks;
+        let rest = heights[start + left % period] - heights[start];
+        return (left / period)
+            .checked_mul(growth)
+            .and_then(|h| h.checked_add(tower.height() + rest))
+            .ok_or_else(|| err!("tower height overflow"));
+    }
+    Ok(tower.height())
+}
+
+pub struct Solver;
+
+impl Solution for Solver {
+    type Input = Vec<Shift>;
+
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Tower height after 2022 rocks", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Tower height after 10^12 rocks", part2(input)?))
+    }
+}
+
+const SHAPES: [Shape; 5] = [
+    Shape::Minus,
+    Shape::Plus,
+    Shape::Corner,
+    Shape::Line,
+    Shape::Square,
+];
+
+/// Rows at the top of the tower compared when looking for a cycle.
+///
+/// This is a heuristic: a rock could still fall past these rows along a tall overhang,
+/// so two towers with the same surface may not grow the same.
+const SURFACE_ROWS: usize = 64;
+
+/// Rocks falling one after another, pushed by the jets.
+struct Tower<'a> {
+    moves: &'a [Shift],
+    jet: usize,
+    shape: usize,
+    rocks: usize,
+    field: Field,
+}
+
+impl<'a> Tower<'a> {
+    /// Tower of no rocks, `moves` must not be empty.
+    fn new(moves: &'a [Shift]) -> Self {
+        Tower { moves, jet: 0, shape: 0, rocks: 0, field: Field::new() }
+    }
+
+    fn height(&self) -> usize {
+        self.field.dropped + self.field.grid.height()
+    }
+
+    fn next_jet(&mut self) -> Shift {
+        let shift = self.moves[self.jet];
+        self.jet = (self.jet + 1) % self.moves.len();
+        shift
+    }
+
+    fn drop_rock(&mut self) {
+        let shape = SHAPES[self.shape];
+        self.shape = (self.shape + 1) % SHAPES.len();
+        let (mut x, mut y): (usize, usize) = (2, self.field.grid.height() + 3);
         loop {
-            let shift = moves_iter.next().ok_or_else(|| err!("endless iter"))?;
-            field1.shift_to(*shift, shape); 
-            if !field1.fall_down(shape) {
-                break
+            let pushed = match self.next_jet() {
+                Shift::Left => x.checked_sub(1),
+                Shift::Right => Some(x + 1),
+            };
+            if let Some(x1) = pushed.filter(|&x1| self.field.fits(shape, x1, y)) {
+                x = x1;
             }
-        }
-        field1.record(shape);
-        // println!("{}", PrettyBits(&field.field));
-    }
-    // println!("{}", PrettyBits(&field1.field));
-
-    let shapes_iter = (0..shapes.len()).into_iter().cycle();
-    let mut moves_iter = moves.iter().cycle();
-    let mut field2 = Field::new();
-    let dt = time::Instant::now();
-    for (n, shape_idx) in shapes_iter.take(PART2_SHAPES).enumerate() {
-        let shape = shapes[shape_idx];
-        for _ in 0..3 {
-            let shift = moves_iter.next().expect("corrupted cycle iterator");
-            field2.shift_to_unchecked(*shift, &shape);
-        }
-        loop {
-            let shift = moves_iter.next().expect("corrupted cycle iterator");
-            field2.shift_to(*shift, &shape);
-            if !field2.fall_down(&shape) {
+            if y == 0 || !self.field.fits(shape, x, y - 1) {
                 break
             }
+            y -= 1;
         }
-        field2.record(&shape);
-        // if n + 1 == k {
-        //     println!("{}", PrettyBits(&field2.field));
-        // }
-        if n > 0 && n % 100_000 == 0 {
-            let x = n as f64 / PART2_SHAPES as f64;
-            print!("  done {:08.4}%    {:?}\r", 100_f64 * x, dt.elapsed());
-        }
+        self.field.record(shape, x, y);
+        self.rocks += 1;
     }
-    println!("\n===========================================");
-    // println!("{}", PrettyBits(&field2.field));
 
-    Ok((field1.total_height(), field2.total_height()))
+    /// Next shape, next jet and the surface of the tower.
+    fn state(&self) -> (usize, usize, Vec<u8>) {
+        let grid = &self.field.grid;
+        let surface = (grid.height().saturating_sub(SURFACE_ROWS)..grid.height())
+            .map(|y| grid.row(y).iter().fold(0, |bits, &rock| bits << 1 | rock as u8))
+            .collect();
+        (self.shape, self.jet, surface)
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
 enum Shape {
     Minus,
     Plus,
-    LShape,
+    Corner,
     Line,
     Square,
 }
 
 impl Shape {
-    fn width(&self) -> usize {
+    /// Cells of the rock as `(x, y)` from its bottom left corner, upwards.
+    fn cells(self) -> &'static [Pos] {
         use Shape::*;
         match self {
-            Minus => 4,
-            Plus => 3,
-            LShape => 3,
-            Line => 1,
-            Square => 2,
+            Minus => &[(0, 0), (1, 0), (2, 0), (3, 0)],
+            Plus => &[(1, 0), (0, 1), (1, 1), (2, 1), (1, 2)],
+            Corner => &[(0, 0), (1, 0), (2, 0), (2, 1), (2, 2)],
+            Line => &[(0, 0), (0, 1), (0, 2), (0, 3)],
+            Square => &[(0, 0), (1, 0), (0, 1), (1, 1)],
         }
     }
-    fn bit_shape(&self, x: usize) -> u32 {
-        // shapes are bottom-up 0-byte is bottom n-th byte is top
-        use Shape::*;
-        let bits = match self {
-            Minus => 0b01111000_00000000_00000000_00000000,
-            Plus => 0b00100000_01110000_00100000_00000000,
-            LShape => 0b01110000_00010000_00010000_00000000,
-            Line => 0b01000000_01000000_01000000_01000000,
-            Square => 0b01100000_01100000_00000000_00000000,
-        };
-        bits >> x
-    }
 }
 
 #[derive(Debug, Clone, Copy)]
-enum Shift {
+pub enum Shift {
     Left,
     Right,
 }
 
+/// Width of the chamber.
+const WIDTH: usize = 7;
+
+/// Rows of the tower from the bottom up, above the rows dropped below a full row.
 #[derive(Debug)]
 struct Field {
-    x: usize,
-    y: usize,
-    h: usize,
-    total_height: usize,
-    field: Vec<u8>,
+    grid: Grid<bool>,
+    dropped: usize,
 }
 
 impl Field {
     fn new() -> Field {
-        Field {
-            x: 2,
-            y: 0,
-            total_height: 0,
-            h: 0,
-            field: [0].repeat(3 + 4),
-        }
+        Field { grid: Grid::new(WIDTH, 0, false), dropped: 0 }
     }
-    fn total_height(&self) -> usize {
-        self.total_height + self.h
+
+    /// Whether `shape` fits at `(x, y)`, the chamber is open above the tower.
+    fn fits(&self, shape: Shape, x: usize, y: usize) -> bool {
+        shape.cells().iter().all(|&(dx, dy)| {
+            x + dx < WIDTH && !self.grid.get((x + dx, y + dy)).copied().unwrap_or(false)
+        })
     }
-    fn record(&mut self, shape: &Shape) {
-        if self.field.len() - self.y < 4 {
-            self.field.extend([0u8; 4]);
-        }
-        let bytes = shape.bit_shape(self.x).to_be_bytes();
-        for (a, b) in self.field.iter_mut().skip(self.y).zip(&bytes) {
-            *a |= b;
-        }
-        if let Some(idx) = self.field.iter().position(|&byte| byte == 0b0111_1111_u8) {
-            self.total_height += idx + 1;
-            self.field = self.field.split_off(idx + 1);
+
+    /// Adds the rock `shape` come to rest at `(x, y)`, drops the rows below a full row.
+    fn record(&mut self, shape: Shape, x: usize, y: usize) {
+        let cells = shape.cells();
+        let top = y + cells.iter().map(|&(_, dy)| dy + 1).max().unwrap_or(0);
+        if top > self.grid.height() {
+            self.grid.resize_rows(top, false);
         }
-        self.x = 2;
-        self.h = self.field.iter().take_while(|&&x| x > 0).count();
-        self.y = self.h;
-    }
-    fn shift_to(&mut self, shift: Shift, shape: &Shape) {
-        let w = shape.width();
-        let x = match shift {
-            Shift::Left if self.x == 0 => return,
-            Shift::Left => self.x - 1,
-            Shift::Right if self.x == 7 - w => return,
-            Shift::Right => self.x + 1,
-        };
-        if collision(self.get_field(self.y), shape.bit_shape(x)) {
-            return
+        for &(dx, dy) in cells {
+            self.grid[(x + dx, y + dy)] = true;
         }
-        self.x = x;
-    }
-    fn shift_to_unchecked(&mut self, shift: Shift, shape: &Shape) {
-        let w = shape.width();
-        let x = match shift {
-            Shift::Left if self.x == 0 => return,
-            Shift::Left => self.x - 1,
-            Shift::Right if self.x == 7 - w => return,
-            Shift::Right => self.x + 1,
-        };
-        self.x = x;
-    }
-    fn fall_down(&mut self, shape: &Shape) -> bool {
-        let y = match self.y.checked_sub(1) {
-            Some(y) if y > self.h => y,
-            Some(y) => {
-                let field = self.get_field(y);
-                if collision(field, shape.bit_shape(self.x)) {
-                    return false
-                }
-                y
-            }
-            None => return false
-        };
-        self.y = y;
-        true
-    }
-
-    fn get_field(&self, y: usize) -> u32 {
-        let mut field = [0u8; 4];
-        if y < self.field.len() {
-            for (i, byte) in self.field[y..].iter().take(4).enumerate() {
-                field[i] = *byte;
-            }
+        // only the rows of the rock may have become full
+        if let Some(full) = (y..top).rev().find(|&y| self.grid.row(y).iter().all(|&rock| rock)) {
+            self.grid.remove_rows(full + 1);
+            self.dropped += full + 1;
         }
-        u32::from_be_bytes(field)
     }
 }
 
-fn collision(field: u32, shape: u32) -> bool {
-    field & shape > 0
-}
-
+#[cfg(test)]
+mod test {
+    use super::{height_after, parse, part1, part2, Tower};
+    static DATA: &str = r#">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>"#;
 
-struct PrettyBits<'a>(&'a [u8]);
-use std::fmt;
+    #[test]
+    fn solution1() {
+        let moves = parse(DATA).expect("invalid input");
+        assert_eq!(part1(&moves).expect("invalid input"), 3068);
+    }
+    #[test]
+    fn solution2() {
+        let moves = parse(DATA).expect("invalid input");
+        assert_eq!(part2(&moves).expect("invalid input"), 1514285714288);
+    }
 
-impl<'a> fmt::Display for PrettyBits<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for word in self.0.iter().rev() {
-            let s = format!("|{:07b}|", word).replace("0", ".").replace("1", "#");
-            write!(f, "{}\n", s)?;
+    #[test]
+    fn heights() {
+        let moves = parse(DATA).expect("invalid input");
+        // heights from simulating every rock
+        for (n, height) in [(0, 0), (1, 1), (2, 4), (10, 17), (2022, 3068), (5000, 7577)] {
+            let mut tower = Tower::new(&moves);
+            for _ in 0..n {
+                tower.drop_rock();
+            }
+            assert_eq!(tower.height(), height);
+            assert_eq!(height_after(&moves, n).expect("invalid input"), height);
         }
-        write!(f, "'-------'")
     }
-}
-
-#[cfg(test)]
-mod test {
-    use super::main;
-    static DATA: &str = r#">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>"#;
 
     #[test]
-    fn solution() {
-        let res = main(DATA).expect("invalid input");
-        assert_eq!(res, (3068, 1514285714288));
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
     }
 }
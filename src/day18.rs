@@ -1,33 +1,77 @@
 use std::str;
-use std::collections::{VecDeque, HashSet};
+use std::collections::HashSet;
 use anyhow::anyhow as err;
 
-pub fn main(data: &str) -> anyhow::Result<(usize, usize)> {
-    let cubes = data.trim().lines().map(|l| l.parse()).collect::<anyhow::Result<HashSet<Coord>>>()?;
-    let mut sides = 0;
-
-    let mut sides2 = 0;
-    let min_x = cubes.iter().map(|c| c.x).min().ok_or_else(|| err!("empty set"))?;
-    let max_x = cubes.iter().map(|c| c.x).max().ok_or_else(|| err!("empty set"))?;
-    let min_y = cubes.iter().map(|c| c.y).min().ok_or_else(|| err!("empty set"))?;
-    let max_y = cubes.iter().map(|c| c.y).max().ok_or_else(|| err!("empty set"))?;
-    let min_z = cubes.iter().map(|c| c.z).min().ok_or_else(|| err!("empty set"))?;
-    let max_z = cubes.iter().map(|c| c.z).max().ok_or_else(|| err!("empty set"))?;
-
-    for cube in cubes.iter() {
-        for c in cube.iter_neighbours().filter(|c| !cubes.contains(c)) {
-            sides += 1;
-            if search_out_of_bounds(c, &cubes, min_x, max_x, min_y, max_y, min_z, max_z) {
-                sides2 += 1;
+use crate::parser::{self, ParseError};
+use crate::solution::{Answer, Solution};
+
+pub fn parse(data: &str) -> anyhow::Result<HashSet<Coord>> {
+    let cubes = parser::lines(data, |line| match line.trim() {
+        "" => Ok(None),
+        _ => line.parse().map(Some),
+    })?;
+    Ok(cubes.into_iter().flatten().collect())
+}
+
+pub fn part1(cubes: &HashSet<Coord>) -> anyhow::Result<usize> {
+    Ok(cubes
+        .iter()
+        .flat_map(|cube| cube.iter_neighbours())
+        .filter(|c| !cubes.contains(c))
+        .count())
+}
+
+pub fn part2(cubes: &HashSet<Coord>) -> anyhow::Result<usize> {
+    Ok(scan(cubes)?.exterior_area)
+}
+
+/// Outside and inside of a droplet.
+#[derive(Debug)]
+pub struct Scan {
+    /// Faces of the cubes in contact with the air around the droplet.
+    pub exterior_area: usize,
+    /// Volumes of the air pockets trapped inside the droplet, one per connected pocket.
+    pub pockets: Vec<usize>,
+}
+
+/// Flood fills the air around the droplet made of `cubes`, then the air pockets left.
+pub fn scan(cubes: &HashSet<Coord>) -> anyhow::Result<Scan> {
+    let grid = Grid::new(cubes)?;
+    let mut seen = Bits::new(grid.cells());
+    // the grid has a layer of air all around the droplet
+    let (_, exterior_area) = grid.fill([0, 0, 0], &mut seen);
+    let mut pockets = Vec::new();
+    for x in 0..grid.size[0] {
+        for y in 0..grid.size[1] {
+            for z in 0..grid.size[2] {
+                let idx = grid.index([x, y, z]);
+                if !grid.cubes.get(idx) && !seen.get(idx) {
+                    pockets.push(grid.fill([x, y, z], &mut seen).0);
+                }
             }
         }
     }
+    Ok(Scan { exterior_area, pockets })
+}
 
-    Ok((sides, sides2))
+pub struct Solver;
+
+impl Solution for Solver {
+    type Input = HashSet<Coord>;
+
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Surface area", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Exterior surface area", part2(input)?))
+    }
 }
 
 #[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
-struct Coord{
+pub struct Coord{
     x: isize,
     y: isize,
     z: isize,
@@ -48,49 +92,119 @@ impl Coord {
 }
 
 impl str::FromStr for Coord {
-    type Err = anyhow::Error;
-    fn from_str(val: &str) -> anyhow::Result<Self> {
-        let (x, yz) = val.split_once(",").ok_or_else(|| err!("expected comma"))?;
-        let (y, z) = yz.split_once(",").ok_or_else(|| err!("expected another comma"))?;
-        Ok(Coord {
-            x: x.parse()?,
-            y: y.parse()?,
-            z: z.parse()?,
-        })
+    type Err = ParseError;
+    fn from_str(val: &str) -> Result<Self, ParseError> {
+        let (x, yz) = parser::split(val, val, ",")?;
+        let (y, z) = parser::split(val, yz, ",")?;
+        // read as i32 so that neighbours of any cube fit into isize
+        let coord = |at| parser::number::<i32>(val, at).map(|c| c as isize);
+        Ok(Coord { x: coord(x)?, y: coord(y)?, z: coord(z)? })
     }
 }
 
-fn search_out_of_bounds(
-    cube: Coord,
-    cubes: &HashSet<Coord>,
-    min_x: isize, max_x: isize,
-    min_y: isize, max_y: isize,
-    min_z: isize, max_z: isize,
-) -> bool {
-    let mut seen = cubes.clone();
-    let mut todo = VecDeque::new();
-    todo.push_back(cube);
-    while !todo.is_empty() {
-        let c = todo.pop_front().expect("corrupted VecDeque");
-        seen.insert(c);
-        for n in c.iter_neighbours().filter(|c| !seen.contains(c)) {
-            if n.x < min_x || n.x > max_x {
-                return true
-            } else if n.y < min_y || n.y > max_y {
-                return true
-            } else if n.z < min_z || n.z > max_z {
-                return true
+/// Largest bounding box of a droplet, in cells.
+const MAX_CELLS: usize = 1 << 27;
+
+/// Dense grid over the bounding box of a droplet grown by one cell on every side,
+/// cells are indexed by their position from the lowest corner.
+struct Grid {
+    size: [usize; 3],
+    cubes: Bits,
+}
+
+impl Grid {
+    fn new(cubes: &HashSet<Coord>) -> anyhow::Result<Grid> {
+        let axes = |c: &Coord| [c.x, c.y, c.z];
+        let mut min = [isize::MAX; 3];
+        let mut max = [isize::MIN; 3];
+        for cube in cubes {
+            for (axis, v) in axes(cube).into_iter().enumerate() {
+                min[axis] = min[axis].min(v - 1);
+                max[axis] = max[axis].max(v + 1);
             }
-            todo.push_front(n);
         }
+        if cubes.is_empty() {
+            return Err(err!("no cubes in the droplet"));
+        }
+        let size = [0, 1, 2].map(|axis| (max[axis] - min[axis] + 1) as usize);
+        let cells = size.iter()
+            .try_fold(1usize, |acc, &n| acc.checked_mul(n))
+            .filter(|&cells| cells <= MAX_CELLS)
+            .ok_or_else(|| err!("droplet larger than {} cells", MAX_CELLS))?;
+        let mut grid = Grid { size, cubes: Bits::new(cells) };
+        for cube in cubes {
+            let c = axes(cube);
+            let idx = grid.index([0, 1, 2].map(|axis| (c[axis] - min[axis]) as usize));
+            grid.cubes.set(idx);
+        }
+        Ok(grid)
+    }
+
+    fn cells(&self) -> usize {
+        self.size.iter().product()
+    }
+
+    fn index(&self, [x, y, z]: [usize; 3]) -> usize {
+        (x * self.size[1] + y) * self.size[2] + z
+    }
+
+    fn neighbours(&self, cell: [usize; 3]) -> impl Iterator<Item=[usize; 3]> + '_ {
+        (0..3).flat_map(move |axis| {
+            let below = cell[axis].checked_sub(1);
+            let above = Some(cell[axis] + 1).filter(|&v| v < self.size[axis]);
+            [below, above].into_iter().flatten().map(move |v| {
+                let mut n = cell;
+                n[axis] = v;
+                n
+            })
+        })
+    }
+
+    /// Marks as `seen` the air connected to `start`,
+    /// returns its volume and the number of cube faces around it.
+    ///
+    /// Not `search::bfs_distances`: its map of distances takes far more memory than
+    /// one bit per cell, and the fill needs no distances.
+    fn fill(&self, start: [usize; 3], seen: &mut Bits) -> (usize, usize) {
+        let (mut volume, mut faces) = (0, 0);
+        let mut todo = vec![start];
+        seen.set(self.index(start));
+        while let Some(cell) = todo.pop() {
+            volume += 1;
+            for n in self.neighbours(cell) {
+                let idx = self.index(n);
+                if self.cubes.get(idx) {
+                    faces += 1;
+                } else if !seen.get(idx) {
+                    seen.set(idx);
+                    todo.push(n);
+                }
+            }
+        }
+        (volume, faces)
     }
-    false
 }
 
+/// Fixed size set of bits.
+struct Bits(Vec<u64>);
+
+impl Bits {
+    fn new(len: usize) -> Bits {
+        Bits(vec![0; len.div_ceil(64)])
+    }
+
+    fn get(&self, idx: usize) -> bool {
+        self.0[idx / 64] & 1 << (idx % 64) != 0
+    }
+
+    fn set(&mut self, idx: usize) {
+        self.0[idx / 64] |= 1 << (idx % 64);
+    }
+}
 
 #[cfg(test)]
 mod test {
-    use super::main;
+    use super::{parse, part1, part2, scan};
 
     static DATA: &str = r#"
 2,2,2
@@ -109,7 +223,43 @@ mod test {
 "#;
     #[test]
     fn solution() {
-        let res = main(DATA).expect("invalid input");
-        assert_eq!(res, (64, 58));
+        let cubes = parse(DATA).expect("invalid input");
+        assert_eq!(part1(&cubes).expect("invalid input"), 64);
+        assert_eq!(part2(&cubes).expect("invalid input"), 58);
+    }
+
+    /// Hollow box with its lowest corner at `x`, 0, 0 and `n` cubes of air inside along every axis.
+    fn hollow_box(x: isize, n: isize) -> String {
+        let mut cubes = Vec::new();
+        for i in 0..n + 2 {
+            for j in 0..n + 2 {
+                for k in 0..n + 2 {
+                    if [i, j, k].iter().any(|&c| c == 0 || c == n + 1) {
+                        cubes.push(format!("{},{},{}", x + i, j, k));
+                    }
+                }
+            }
+        }
+        cubes.join("\n")
+    }
+
+    #[test]
+    fn pockets() {
+        let cubes = parse(DATA).expect("invalid input");
+        assert_eq!(scan(&cubes).expect("invalid input").pockets, [1]);
+
+        let boxes = parse(&format!("{}\n{}", hollow_box(-7, 3), hollow_box(0, 1))).expect("invalid input");
+        let scan = scan(&boxes).expect("invalid input");
+        assert_eq!(scan.pockets, [27, 1]);
+        assert_eq!(scan.exterior_area, 6 * 25 + 6 * 9);
+        assert_eq!(part1(&boxes).expect("invalid input"), 6 * 25 + 6 * 9 + 6 * 9 + 6);
+
+        let far = parse("0,0,0\n1000000,1000000,1000000").expect("invalid input");
+        assert!(part2(&far).is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
     }
 }
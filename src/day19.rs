@@ -1,20 +1,46 @@
-use anyhow::anyhow as err;
 use std::str;
+use anyhow::anyhow as err;
+use rayon::prelude::*;
+
+use crate::parser::{self, ParseError};
+use crate::solution::{Answer, Solution};
 
-const T: usize = 24;
+pub fn parse(data: &str) -> anyhow::Result<Vec<Blueprint>> {
+    Ok(parser::lines(data, str::parse)?)
+}
 
-pub fn main(data: &str) -> anyhow::Result<(usize, usize)> {
-    let blueprints = data
-        .lines()
-        .map(|line| line.parse())
-        .collect::<anyhow::Result<Vec<Blueprint>>>()?;
-    println!("Data: {:?}", blueprints);
+pub fn part1(blueprints: &[Blueprint]) -> anyhow::Result<usize> {
+    let geodes: Vec<_> = blueprints.par_iter().map(|b| b.max_geodes(24)).collect();
+    blueprints
+        .iter()
+        .zip(geodes)
+        .try_fold(0usize, |sum, (b, geodes)| sum.checked_add(b.index.checked_mul(geodes)?))
+        .ok_or_else(|| err!("quality levels overflow"))
+}
 
-    Ok((0, 0))
+pub fn part2(blueprints: &[Blueprint]) -> anyhow::Result<usize> {
+    let first = &blueprints[..blueprints.len().min(3)];
+    Ok(first.par_iter().map(|b| b.max_geodes(32)).product())
+}
+
+pub struct Solver;
+
+impl Solution for Solver {
+    type Input = Vec<Blueprint>;
+
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Quality levels sum", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("First three blueprints geodes product", part2(input)?))
+    }
 }
 
 #[derive(Debug)]
-struct Blueprint {
+pub struct Blueprint {
     index: usize,
     ore_robot: Cost,
     clay_robot: Cost,
@@ -22,7 +48,6 @@ struct Blueprint {
     geode_robot: Cost,
 }
 
-
 #[derive(Debug, Default)]
 struct Cost {
     ore: usize,
@@ -30,36 +55,165 @@ struct Cost {
     obsidian: usize,
 }
 
-impl str::FromStr for Blueprint {
-    type Err = anyhow::Error;
-    fn from_str(val: &str) -> anyhow::Result<Self> {
-        let (prefix, tail) = val.split_once(":").ok_or_else(|| err!("expected ':'"))?;
-        let index = prefix["Blueprint ".len()..].parse()?;
-        let robot_lines = tail
-            .split(". ")
-            .map(|w| w.trim_matches(&['.', ' '][..]))
-            .collect::<Vec<_>>();
-        let ore_robot = robot_lines[0]["Each ore robot costs ".len()..].parse()?;
-        let clay_robot = robot_lines[1]["Each clay robot costs ".len()..].parse()?;
-        let obsidian_robot = robot_lines[2]["Each obsidian robot costs ".len()..].parse()?;
-        let geode_robot = robot_lines[3]["Each geode robot costs ".len()..].parse()?;
+/// Collected resources and robots, with `time` minutes left.
+#[derive(Debug, Clone, Copy)]
+struct State {
+    time: usize,
+    ore: usize,
+    clay: usize,
+    obsidian: usize,
+    ore_robots: usize,
+    clay_robots: usize,
+    obsidian_robots: usize,
+    /// Geodes opened by the end, counting the whole lifetime of the geode robots built so far.
+    geodes: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Robot {
+    Ore,
+    Clay,
+    Obsidian,
+    Geode,
+}
+
+impl Blueprint {
+    /// Most geodes that can be opened in `time` minutes, by branch and bound over the order
+    /// in which robots are built.
+    fn max_geodes(&self, time: usize) -> usize {
+        let start = State {
+            time,
+            ore: 0,
+            clay: 0,
+            obsidian: 0,
+            ore_robots: 1,
+            clay_robots: 0,
+            obsidian_robots: 0,
+            geodes: 0,
+        };
+        let mut best = 0;
+        self.search(start, &mut best);
+        best
+    }
 
+    fn search(&self, state: State, best: &mut usize) {
+        *best = (*best).max(state.geodes);
+        if self.upper_bound(&state) <= *best {
+            return
+        }
+        // a factory builds one robot per minute, so more robots than the biggest
+        // spending of a resource per minute never help
+        let max_ore = [&self.ore_robot, &self.clay_robot, &self.obsidian_robot, &self.geode_robot]
+            .iter()
+            .map(|c| c.ore)
+            .max()
+            .unwrap_or(0);
+        let useful = [
+            (Robot::Geode, true),
+            (Robot::Obsidian, state.obsidian_robots < self.geode_robot.obsidian),
+            (Robot::Clay, state.clay_robots < self.obsidian_robot.clay),
+            (Robot::Ore, state.ore_robots < max_ore),
+        ];
+        for (robot, _) in useful.iter().filter(|(_, useful)| *useful) {
+            if let Some(next) = self.build(&state, *robot) {
+                self.search(next, best);
+            }
+        }
+    }
+
+    /// Waits until the robot can be built and builds it, `None` if it would not be ready
+    /// before the time runs out.
+    fn build(&self, state: &State, robot: Robot) -> Option<State> {
+        let cost = match robot {
+            Robot::Ore => &self.ore_robot,
+            Robot::Clay => &self.clay_robot,
+            Robot::Obsidian => &self.obsidian_robot,
+            Robot::Geode => &self.geode_robot,
+        };
+        let wait = [
+            wait(cost.ore, state.ore, state.ore_robots)?,
+            wait(cost.clay, state.clay, state.clay_robots)?,
+            wait(cost.obsidian, state.obsidian, state.obsidian_robots)?,
+        ].into_iter().max().unwrap_or(0);
+        // the robot is useful only from the minute after it is built
+        let elapsed = wait + 1;
+        if elapsed >= state.time {
+            return None
+        }
+        let mut next = State {
+            time: state.time - elapsed,
+            ore: state.ore + state.ore_robots * elapsed - cost.ore,
+            clay: state.clay + state.clay_robots * elapsed - cost.clay,
+            obsidian: state.obsidian + state.obsidian_robots * elapsed - cost.obsidian,
+            ..*state
+        };
+        match robot {
+            Robot::Ore => next.ore_robots += 1,
+            Robot::Clay => next.clay_robots += 1,
+            Robot::Obsidian => next.obsidian_robots += 1,
+            Robot::Geode => next.geodes += next.time,
+        }
+        Some(next)
+    }
+
+    /// Geodes opened if obsidian robots were free and one was built every minute when
+    /// a geode robot cannot be.
+    fn upper_bound(&self, state: &State) -> usize {
+        let (mut obsidian, mut robots, mut geodes) = (state.obsidian, state.obsidian_robots, state.geodes);
+        for time in (0..state.time).rev() {
+            if obsidian >= self.geode_robot.obsidian {
+                obsidian = obsidian - self.geode_robot.obsidian + robots;
+                geodes += time;
+            } else {
+                obsidian += robots;
+                robots += 1;
+            }
+        }
+        geodes
+    }
+}
+
+/// Minutes to wait until `cost` is collected, `None` if there are no robots collecting it.
+fn wait(cost: usize, have: usize, robots: usize) -> Option<usize> {
+    match cost.saturating_sub(have) {
+        0 => Some(0),
+        _ if robots == 0 => None,
+        missing => Some(missing.div_ceil(robots)),
+    }
+}
+
+impl str::FromStr for Blueprint {
+    type Err = ParseError;
+    fn from_str(val: &str) -> Result<Self, ParseError> {
+        let rest = parser::prefix(val, val, "Blueprint ")?;
+        let (index, mut rest) = parser::split(val, rest, ":")?;
+        let index = parser::number(val, index)?;
+        let mut robot = |name| -> Result<Cost, ParseError> {
+            let sentence = parser::prefix(val, rest.trim_start(), &format!("Each {} robot costs ", name))?;
+            let (price, tail) = parser::split(val, sentence, ".")?;
+            rest = tail;
+            price.parse().map_err(|e: ParseError| e.within(val, price))
+        };
+        let ore_robot = robot("ore")?;
+        let clay_robot = robot("clay")?;
+        let obsidian_robot = robot("obsidian")?;
+        let geode_robot = robot("geode")?;
         Ok(Blueprint { index, ore_robot, clay_robot, obsidian_robot, geode_robot })
     }
 }
 
 impl str::FromStr for Cost {
-    type Err = anyhow::Error;
-    fn from_str(val: &str) -> anyhow::Result<Self> {
+    type Err = ParseError;
+    fn from_str(val: &str) -> Result<Self, ParseError> {
         let mut cost = Cost::default();
         for part in val.split(" and ") {
-            let (x, typ) = part.split_once(" ").ok_or_else(|| err!("expected cost"))?;
-            let x: usize = x.parse()?;
+            let (x, typ) = parser::split(val, part, " ")?;
+            let x: usize = parser::number(val, x)?;
             match typ {
                 "ore" => cost.ore = x,
                 "clay" => cost.clay = x,
                 "obsidian" => cost.obsidian = x,
-                _ => return Err(err!("unexpected cost type: {}", part))
+                _ => return Err(ParseError::at(val, typ, "expected 'ore', 'clay' or 'obsidian'")),
             }
         }
         Ok(cost)
@@ -68,7 +222,7 @@ impl str::FromStr for Cost {
 
 #[cfg(test)]
 mod test {
-    use super::main;
+    use super::{parse, part1, part2};
 
     static DATA: &str = concat!(
         "Blueprint 1:",
@@ -86,7 +240,13 @@ mod test {
 
     #[test]
     fn solution() {
-        let res = main(DATA).expect("invalid input");
-        assert_eq!(res, (33, 0));
+        let blueprints = parse(DATA).expect("invalid input");
+        assert_eq!(part1(&blueprints).expect("invalid input"), 33);
+        assert_eq!(part2(&blueprints).expect("invalid input"), 56 * 62);
+    }
+
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
     }
 }
@@ -1,35 +1,72 @@
-pub fn main(data: &str) -> anyhow::Result<(usize, usize)> {
-    let mut part1: usize = 0;
-    for line in data.lines() {
-        let mut split = line.split(' ').take(2);
-        let a = split
-            .next()
-            .map(|l| Hand::from_known(l))
-            .ok_or(anyhow::anyhow!("Bad input line"))?;
-        let b = split
-            .next()
-            .map(|l| Hand::from_guessed(l))
-            .ok_or(anyhow::anyhow!("Bad input line"))?;
-        part1 += b.score_over(&a);
-        part1 += b.score();
+use crate::parser::{self, ParseError};
+use crate::solution::{Answer, Solution};
+
+pub fn parse(data: &str) -> anyhow::Result<Vec<Round>> {
+    let rounds = parser::lines(data, |line| {
+        let (a, b) = parser::split(line, line, " ")?;
+        let hand = Hand::from_known(a).ok_or_else(|| ParseError::at(line, a, "expected A, B or C"))?;
+        let hint = Hint::from_str(b).ok_or_else(|| ParseError::at(line, b, "expected X, Y or Z"))?;
+        Ok(Round(hand, hint))
+    })?;
+    Ok(rounds)
+}
+
+pub fn part1(rounds: &[Round]) -> anyhow::Result<usize> {
+    let mut score: usize = 0;
+    for Round(a, b) in rounds {
+        let b = Hand::from_guessed(b);
+        score += b.score_over(a);
+        score += b.score();
     }
+    Ok(score)
+}
 
-    let mut part2: usize = 0;
-    for line in data.lines() {
-        let mut split = line.split(' ').take(2);
-        let a = split
-            .next()
-            .map(|l| Hand::from_known(l))
-            .ok_or(anyhow::anyhow!("Bad input line"))?;
-        let b = split
-            .next()
-            .map(|l| RoundResult::from_str(l))
-            .ok_or(anyhow::anyhow!("Bad input line"))?;
-        let hand = b.get_hand(&a);
-        part2 += hand.score_over(&a);
-        part2 += hand.score();
+pub fn part2(rounds: &[Round]) -> anyhow::Result<usize> {
+    let mut score: usize = 0;
+    for Round(a, b) in rounds {
+        let hand = RoundResult::from_hint(b).get_hand(a);
+        score += hand.score_over(a);
+        score += hand.score();
+    }
+    Ok(score)
+}
+
+pub struct Solver;
+
+impl Solution for Solver {
+    type Input = Vec<Round>;
+
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Guessed score", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Real score", part2(input)?))
+    }
+}
+
+/// Opponent's hand and the second column of the strategy guide.
+pub struct Round(Hand, Hint);
+
+/// Second column of the strategy guide, its meaning differs between parts.
+#[derive(Clone, Copy)]
+enum Hint {
+    X,
+    Y,
+    Z,
+}
+
+impl Hint {
+    fn from_str(val: &str) -> Option<Self> {
+        match val {
+            "X" => Some(Hint::X),
+            "Y" => Some(Hint::Y),
+            "Z" => Some(Hint::Z),
+            _ => None,
+        }
     }
-    Ok((part1, part2))
 }
 
 #[derive(Clone, Copy)]
@@ -58,21 +95,20 @@ impl Hand {
         }
     }
 
-    fn from_known(val: &str) -> Self {
+    fn from_known(val: &str) -> Option<Self> {
         match val {
-            "A" => Hand::Rock,
-            "B" => Hand::Paper,
-            "C" => Hand::Scissors,
-            _ => unreachable!(),
+            "A" => Some(Hand::Rock),
+            "B" => Some(Hand::Paper),
+            "C" => Some(Hand::Scissors),
+            _ => None,
         }
     }
 
-    fn from_guessed(val: &str) -> Self {
-        match val {
-            "X" => Hand::Rock,
-            "Y" => Hand::Paper,
-            "Z" => Hand::Scissors,
-            _ => unreachable!(),
+    fn from_guessed(hint: &Hint) -> Self {
+        match hint {
+            Hint::X => Hand::Rock,
+            Hint::Y => Hand::Paper,
+            Hint::Z => Hand::Scissors,
         }
     }
 }
@@ -84,13 +120,12 @@ enum RoundResult {
 }
 
 impl RoundResult {
-    fn from_str(val: &str) -> Self {
+    fn from_hint(hint: &Hint) -> Self {
         use RoundResult::*;
-        match val {
-            "X" => Lose,
-            "Y" => Draw,
-            "Z" => Win,
-            _ => unreachable!(),
+        match hint {
+            Hint::X => Lose,
+            Hint::Y => Draw,
+            Hint::Z => Win,
         }
     }
     fn get_hand(&self, other: &Hand) -> Hand {
@@ -110,14 +145,19 @@ impl RoundResult {
 
 #[cfg(test)]
 mod test {
-    use super::main;
+    use super::{parse, part1, part2};
 
     static DATA: &str = concat!("A Y\n", "B X\n", "C Z\n",);
 
     #[test]
     fn solution() {
-        let (a, b) = main(DATA).expect("Invalid input");
-        assert_eq!(a, 15);
-        assert_eq!(b, 12);
+        let rounds = parse(DATA).expect("Invalid input");
+        assert_eq!(part1(&rounds).expect("Invalid input"), 15);
+        assert_eq!(part2(&rounds).expect("Invalid input"), 12);
+    }
+
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
     }
 }
@@ -1,69 +1,227 @@
 use anyhow::anyhow as err;
 
-pub fn main(data: &str) -> anyhow::Result<(i64, i64)> {
-    let mut values = Vec::with_capacity(data.trim().lines().count());
-    for (index, line) in data.trim().lines().enumerate() {
-        let value = line.parse()?;
-        values.push(N { value, index });
-    }
-
-    let mut part1 = values.clone();
-    shuffle_values(&mut part1)?;
-    let it = part1.iter().cycle().skip_while(|v| v.value != 0);
-    let a1 = it.clone().nth(1000).ok_or_else(|| err!("corrupted iterator"))?.value;
-    let b1 = it.clone().nth(2000).ok_or_else(|| err!("corrupted iterator"))?.value;
-    let c1 = it.clone().nth(3000).ok_or_else(|| err!("corrupted iterator"))?.value;
-
-    let decr_key: i64 = 811589153;
-
-    let mut part2 = values
-        .into_iter()
-        .map(|N{value, index}| N { index, value: decr_key * value})
-        .collect();
-    for _ in 0..10 {
-        shuffle_values(&mut part2)?;
-    }
-    let it = part2.iter().cycle().skip_while(|v| v.value != 0);
-    let a2 = it.clone().nth(1000).ok_or_else(|| err!("corrupted iterator"))?.value;
-    let b2 = it.clone().nth(2000).ok_or_else(|| err!("corrupted iterator"))?.value;
-    let c2 = it.clone().nth(3000).ok_or_else(|| err!("corrupted iterator"))?.value;
-
-    Ok((a1 + b1 + c1, a2 + b2 + c2))
+use crate::parser;
+use crate::solution::{Answer, Solution};
+
+const DECRYPTION_KEY: i64 = 811589153;
+
+pub fn parse(data: &str) -> anyhow::Result<Vec<i64>> {
+    let values = parser::lines(data, |line| match line.trim() {
+        "" => Ok(None),
+        number => parser::number(line, number).map(Some),
+    })?;
+    Ok(values.into_iter().flatten().collect())
+}
+
+pub fn part1(values: &[i64]) -> anyhow::Result<i64> {
+    grove_coordinates(&mix(values, 1, 1)?)
 }
 
+pub fn part2(values: &[i64]) -> anyhow::Result<i64> {
+    grove_coordinates(&mix(values, DECRYPTION_KEY, 10)?)
+}
+
+/// Multiplies the values by `key` and mixes them `rounds` times: every value in the
+/// original order moves forward or backward by itself in the circular sequence.
+///
+/// Returns the mixed sequence, starting anywhere on the circle.
+pub fn mix(values: &[i64], key: i64, rounds: usize) -> anyhow::Result<Vec<i64>> {
+    let values = values
+        .iter()
+        .map(|value| value.checked_mul(key).ok_or_else(|| err!("value {} too large", value)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if values.len() < 2 {
+        return Ok(values)
+    }
+    let mut sequence = Sequence::new(values.len());
+    // a value moving around the circle passes the other values only
+    let others = values.len() as i64 - 1;
+    for _ in 0..rounds {
+        for (id, &value) in values.iter().enumerate() {
+            let from = sequence.position(id);
+            sequence.remove(from);
+            let to = (from as i64 + value.rem_euclid(others)) % others;
+            sequence.insert(to as usize, id);
+        }
+    }
+    Ok(sequence.to_vec().into_iter().map(|id| values[id]).collect())
+}
+
+pub struct Solver;
+
+impl Solution for Solver {
+    type Input = Vec<i64>;
+
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Grove coordinates sum", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Decrypted grove coordinates sum", part2(input)?))
+    }
+}
+
+fn grove_coordinates(values: &[i64]) -> anyhow::Result<i64> {
+    let zero = values
+        .iter()
+        .position(|&v| v == 0)
+        .ok_or_else(|| err!("no value 0 in the file"))?;
+    [1000, 2000, 3000]
+        .iter()
+        .map(|n| values[(zero + n) % values.len()])
+        .try_fold(0i64, |acc, v| acc.checked_add(v))
+        .ok_or_else(|| err!("grove coordinates sum overflow"))
+}
+
+const NIL: usize = usize::MAX;
+
 #[derive(Debug, Clone, Copy)]
-struct N {
-    value: i64,
-    index: usize,
+struct Node {
+    left: usize,
+    right: usize,
+    parent: usize,
+    /// Number of nodes in the subtree.
+    size: usize,
+    priority: u64,
+}
+
+/// Sequence of the ids `0..n` as an implicit treap: a binary tree ordered by position,
+/// kept balanced by random priorities. Nodes are indexed by id, and parent links give
+/// the position of an id, so that moving an id takes O(log n).
+#[derive(Debug)]
+struct Sequence {
+    nodes: Vec<Node>,
+    root: usize,
 }
 
-fn shuffle_values(values: &mut Vec<N>) -> anyhow::Result<()> {
-    for i in 0..values.len() {
-        let (from, &item) = values
-            .iter()
-            .enumerate()
-            .find(|(_, v)| v.index == i)
-            .ok_or_else(|| err!("not found {}", i))?;
-        if item.value == 0 {
-            continue;
+impl Sequence {
+    /// Ids from 0 to `n - 1` in order.
+    fn new(n: usize) -> Self {
+        // xorshift, the priorities only have to look random
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let nodes = (0..n).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            Node { left: NIL, right: NIL, parent: NIL, size: 1, priority: state }
+        }).collect();
+        let mut sequence = Sequence { nodes, root: NIL };
+        for id in 0..n {
+            sequence.root = sequence.merge(sequence.root, id);
         }
-        let value = item.value;
-        values.remove(from);
-        if value < 0 {
-            let r = value.abs() as usize % values.len();
-            values.rotate_right(r);
+        sequence
+    }
+
+    fn size(&self, t: usize) -> usize {
+        if t == NIL { 0 } else { self.nodes[t].size }
+    }
+
+    /// Recomputes the size of `t` after its children changed.
+    fn update(&mut self, t: usize) {
+        let Node { left, right, .. } = self.nodes[t];
+        self.nodes[t].size = 1 + self.size(left) + self.size(right);
+        for child in [left, right] {
+            if child != NIL {
+                self.nodes[child].parent = t;
+            }
+        }
+    }
+
+    fn detach(&mut self, t: usize) -> usize {
+        if t != NIL {
+            self.nodes[t].parent = NIL;
+        }
+        t
+    }
+
+    /// Splits the tree `t` into its first `k` nodes and the rest.
+    fn split(&mut self, t: usize, k: usize) -> (usize, usize) {
+        if t == NIL {
+            return (NIL, NIL)
+        }
+        let Node { left, right, .. } = self.nodes[t];
+        let (a, b) = if self.size(left) >= k {
+            let (a, b) = self.split(left, k);
+            self.nodes[t].left = b;
+            (a, t)
+        } else {
+            let (a, b) = self.split(right, k - self.size(left) - 1);
+            self.nodes[t].right = a;
+            (t, b)
+        };
+        self.update(t);
+        (self.detach(a), self.detach(b))
+    }
+
+    /// Joins the trees `a` and `b`, with the nodes of `a` first.
+    fn merge(&mut self, a: usize, b: usize) -> usize {
+        if a == NIL || b == NIL {
+            return self.detach(if a == NIL { b } else { a })
+        }
+        let root = if self.nodes[a].priority > self.nodes[b].priority {
+            self.nodes[a].right = self.merge(self.nodes[a].right, b);
+            a
         } else {
-            let r = value as usize % values.len();
-            values.rotate_left(r);
+            self.nodes[b].left = self.merge(a, self.nodes[b].left);
+            b
+        };
+        self.update(root);
+        self.detach(root)
+    }
+
+    /// Position of `id` in the sequence.
+    fn position(&self, id: usize) -> usize {
+        let mut position = self.size(self.nodes[id].left);
+        let mut t = id;
+        while self.nodes[t].parent != NIL {
+            let parent = self.nodes[t].parent;
+            if self.nodes[parent].right == t {
+                position += self.size(self.nodes[parent].left) + 1;
+            }
+            t = parent;
+        }
+        position
+    }
+
+    /// Removes the id at `position`, which must be in the sequence.
+    fn remove(&mut self, position: usize) -> usize {
+        let (a, rest) = self.split(self.root, position);
+        let (id, b) = self.split(rest, 1);
+        self.root = self.merge(a, b);
+        id
+    }
+
+    /// Inserts a removed `id` before `position`.
+    fn insert(&mut self, position: usize, id: usize) {
+        let (a, b) = self.split(self.root, position);
+        let a = self.merge(a, id);
+        self.root = self.merge(a, b);
+    }
+
+    fn to_vec(&self) -> Vec<usize> {
+        let mut out = Vec::with_capacity(self.size(self.root));
+        let mut stack = Vec::new();
+        let mut t = self.root;
+        while t != NIL || !stack.is_empty() {
+            while t != NIL {
+                stack.push(t);
+                t = self.nodes[t].left;
+            }
+            if let Some(top) = stack.pop() {
+                out.push(top);
+                t = self.nodes[top].right;
+            }
         }
-        values.insert(from, item);
+        out
     }
-    Ok(())
 }
 
 #[cfg(test)]
 mod test {
-    use super::main;
+    use super::{mix, parse, part1, part2};
+    use crate::fuzz::Rng;
     static DATA: &str = r#"
 1
 2
@@ -75,7 +233,46 @@ mod test {
 
     #[test]
     fn solution() {
-        let res = main(DATA).expect("invalid input");
-        assert_eq!(res, (3, 1623178306));
+        let values = parse(DATA).expect("invalid input");
+        assert_eq!(part1(&values).expect("invalid input"), 3);
+        assert_eq!(part2(&values).expect("invalid input"), 1623178306);
+    }
+
+    /// Mixing by moving values around in a `Vec`, in O(n) per move.
+    fn mix_slowly(values: &[i64], key: i64, rounds: usize) -> Vec<i64> {
+        let mut sequence: Vec<_> = values.iter().map(|v| v * key).enumerate().collect();
+        let others = values.len() as i64 - 1;
+        for _ in 0..rounds {
+            for id in 0..values.len() {
+                let from = sequence.iter().position(|&(i, _)| i == id).expect("id in sequence");
+                let (_, value) = sequence.remove(from);
+                let to = (from as i64 + value).rem_euclid(others) as usize;
+                sequence.insert(to, (id, value));
+            }
+        }
+        sequence.into_iter().map(|(_, v)| v).collect()
+    }
+
+    #[test]
+    fn mixing() {
+        let mut rng = Rng::new(20);
+        for _ in 0..100 {
+            let values: Vec<i64> = (0..2 + rng.below(50)).map(|_| rng.below(41) as i64 - 20).collect();
+            let (key, rounds) = (rng.below(1000) as i64 + 1, rng.below(4));
+            assert_eq!(
+                mix(&values, key, rounds).expect("invalid input"),
+                mix_slowly(&values, key, rounds),
+                "{:?} {} {}",
+                values,
+                key,
+                rounds,
+            );
+        }
+        assert_eq!(mix(&[7], 3, 2).expect("invalid input"), [21]);
+    }
+
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
     }
 }
@@ -1,144 +1,263 @@
+use std::fmt;
 use std::str;
-use std::collections::{HashSet, HashMap};
+use std::collections::HashMap;
+
 use anyhow::anyhow as err;
 
-pub fn main(data: &str) -> anyhow::Result<(i64, i64)> {
-    let n = data.trim().lines().count();
-    let mut yelled: HashSet<String> = HashSet::with_capacity(n);
-    let mut gin: HashMap<String, HashSet<String>> = HashMap::new();
-    let mut monkeys: HashMap<String, Yell> = HashMap::with_capacity(n);
-
-    for line in data.trim().lines() {
-        let (name, yell) = line.split_once(": ")
-            .ok_or_else(|| err!("unexpected line: {}", line))?;
-        let yell: Yell = yell.parse()?;
-        // println!("Monkey '{}' yells {:?}", name, yell);
-        if let Some([monkey1, monkey2]) = yell.waits() {
-            gin.entry(monkey1.to_string())
-                .or_default()
-                .insert(name.to_string());
-            gin.entry(monkey2.to_string())
-                .or_default()
-                .insert(name.to_string());
+use crate::parser::{self, ParseError};
+use crate::solution::{Answer, Solution};
+
+const ROOT: &str = "root";
+const HUMAN: &str = "humn";
+
+pub fn parse(data: &str) -> anyhow::Result<HashMap<String, Yell>> {
+    let monkeys = parser::lines(data, |line| {
+        if line.trim().is_empty() {
+            return Ok(None)
         }
-        monkeys.insert(name.to_string(), yell);
+        let (name, yell) = parser::split(line, line, ": ")?;
+        let yell = yell.parse().map_err(|e: ParseError| e.within(line, yell))?;
+        Ok(Some((name.to_string(), yell)))
+    })?;
+    Ok(monkeys.into_iter().flatten().collect())
+}
+
+pub fn part1(monkeys: &HashMap<String, Yell>) -> anyhow::Result<i64> {
+    let tree = Tree::build(monkeys, ROOT, None)?;
+    tree.values()?[tree.root].ok_or_else(|| err!("the number yelled by {} is unknown", ROOT))
+}
+
+/// Finds the number to yell for `root` to see both of its numbers equal.
+pub fn part2(monkeys: &HashMap<String, Yell>) -> anyhow::Result<i64> {
+    if let Some(Yell::Number(_)) = monkeys.get(ROOT) {
+        return Err(err!("{} monkey does not compare numbers", ROOT))
+    }
+    let tree = Tree::build(monkeys, ROOT, Some(HUMAN))?;
+    let Node::Op(_, a, b) = tree.nodes[tree.root] else {
+        return Err(err!("{} monkey does not compare numbers", ROOT))
+    };
+    let values = tree.values()?;
+    match (values[a], values[b]) {
+        (None, None) => Err(err!("{} appears on both sides of the equality", HUMAN)),
+        (None, Some(b)) => tree.solve(&values, a, b),
+        (Some(a), None) => tree.solve(&values, b, a),
+        (Some(_), Some(_)) => Err(err!("{} does not depend on {}", ROOT, HUMAN)),
     }
+}
 
-    loop {
-        let yelling = monkeys
-            .iter()
-            .filter(|(n, y)| y.is_yelling() & !yelled.contains(n.as_str()))
-            .map(|(n, y)| (n.clone(), y.clone()))
-            .collect::<Vec<(String, Yell)>>();
-        if yelling.is_empty() {
-            break
-        }
-        for (name, yell) in yelling {
-            for to in gin.remove(&name).iter().flatten() {
-                let monkey = monkeys.get_mut(to).ok_or_else(|| err!("unknown monkey"))?;
-                monkey.hear(yell.number(), &name);
-            }
-            yelled.insert(name);
-        }
+pub struct Solver;
+
+impl Solution for Solver {
+    type Input = HashMap<String, Yell>;
+
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Root monkey yells", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Human yells", part2(input)?))
     }
-    let root = monkeys.get("root").ok_or_else(|| err!("monkey is missing"))?;
-    assert!(root.is_yelling());
-    Ok((root.number(), 0))
 }
 
 #[derive(Debug, Clone)]
-enum Yell {
-    Add(Arg, Arg),
-    Sub(Arg, Arg),
-    Mult(Arg, Arg),
-    Div(Arg, Arg),
+pub enum Yell {
     Number(i64),
+    /// Operation on numbers yelled by two other monkeys.
+    Op(Op, String, String),
 }
 
-#[derive(Debug, Clone)]
-enum Arg {
-    Wait(String),
-    Ready(i64),
+#[derive(Debug, Clone, Copy)]
+pub enum Op {
+    Add,
+    Sub,
+    Mult,
+    Div,
 }
 
-impl Yell {
-    fn waits(&self) -> Option<[&str; 2]> {
-        use Yell::*;
-        use Arg::*;
-        match self {
-            Number(_) => None,
-            Add(Wait(a), Wait(b)) => Some([&a, &b]),
-            Sub(Wait(a), Wait(b)) => Some([&a, &b]),
-            Mult(Wait(a), Wait(b)) => Some([&a, &b]),
-            Div(Wait(a), Wait(b)) => Some([&a, &b]),
-            _ => unreachable!("waits() must be called before yelling"),
-        }
+impl Op {
+    fn apply(self, a: i64, b: i64) -> anyhow::Result<i64> {
+        let result = match self {
+            Op::Add => a.checked_add(b),
+            Op::Sub => a.checked_sub(b),
+            Op::Mult => a.checked_mul(b),
+            Op::Div => exact_div(a, b),
+        };
+        result.ok_or_else(|| err!("cannot compute {} {} {}", a, self, b))
     }
+}
+
+/// `a / b`, `None` on division by zero, overflow or a remainder.
+fn exact_div(a: i64, b: i64) -> Option<i64> {
+    (a.checked_rem(b)? == 0).then(|| a / b)
+}
+
+/// Expression yelled by a monkey, with the monkeys it waits for resolved to node ids.
+#[derive(Debug, Clone, Copy)]
+enum Node {
+    Number(i64),
+    /// The number yelled by you, unknown.
+    Human,
+    Op(Op, usize, usize),
+}
+
+/// Expressions of the monkeys `root` waits for, each monkey once even when several wait for it.
+/// Nodes come after the nodes they wait for.
+#[derive(Debug)]
+struct Tree {
+    nodes: Vec<Node>,
+    /// Whether the node waits for the number yelled by you.
+    has_human: Vec<bool>,
+    root: usize,
+}
 
-    fn is_yelling(&self) -> bool {
-        matches!(self, Yell::Number(_))
+impl Tree {
+    /// Expressions of the monkey `root`, `human` is the name of the monkey standing for you.
+    fn build(monkeys: &HashMap<String, Yell>, root: &str, human: Option<&str>) -> anyhow::Result<Tree> {
+        // monkeys waiting for others, then their ids once built
+        let mut ids: HashMap<&str, Option<usize>> = HashMap::new();
+        let mut tree = Tree { nodes: Vec::new(), has_human: Vec::new(), root: 0 };
+        // depth-first, a monkey is built when popped the second time
+        let mut stack = vec![(root, false)];
+        while let Some((name, expanded)) = stack.pop() {
+            let yell = monkeys.get(name).ok_or_else(|| err!("monkey {} is missing", name))?;
+            let node = match (yell, expanded) {
+                (Yell::Op(op, a, b), true) => {
+                    let id = |name: &str| ids[name].expect("built before the monkeys waiting for it");
+                    Node::Op(*op, id(a), id(b))
+                }
+                _ => match ids.get(name) {
+                    Some(Some(_)) => continue,
+                    // only the monkeys it waits for are above it on the stack
+                    Some(None) => return Err(err!("monkey {} waits for its own number", name)),
+                    None if human == Some(name) => Node::Human,
+                    None => match yell {
+                        Yell::Number(n) => Node::Number(*n),
+                        Yell::Op(_, a, b) => {
+                            ids.insert(name, None);
+                            stack.extend([(name, true), (b, false), (a, false)]);
+                            continue
+                        }
+                    },
+                },
+            };
+            ids.insert(name, Some(tree.nodes.len()));
+            tree.has_human.push(match node {
+                Node::Number(_) => false,
+                Node::Human => true,
+                Node::Op(_, a, b) => tree.has_human[a] || tree.has_human[b],
+            });
+            tree.nodes.push(node);
+        }
+        tree.root = ids[root].expect("root built last");
+        Ok(tree)
     }
-    fn number(&self) -> i64 {
-        match self {
-            Yell::Number(n) => *n,
-            _ => unreachable!("must have checked if monkey is_yelling()"),
+
+    /// Number yelled by every monkey, `None` for those waiting for you.
+    fn values(&self) -> anyhow::Result<Vec<Option<i64>>> {
+        let mut values = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let value = match *node {
+                Node::Number(n) => Some(n),
+                Node::Human => None,
+                Node::Op(op, a, b) => match (values[a], values[b]) {
+                    (Some(a), Some(b)) => Some(op.apply(a, b)?),
+                    _ => None,
+                },
+            };
+            values.push(value);
         }
+        Ok(values)
     }
 
-    fn hear(&mut self, number: i64, from: &str) {
-        use Yell::*;
-        use Arg::*;
-        let next = match self.clone() {
-            Add(Wait(name), b) if name == from => Add(Ready(number), b),
-            Sub(Wait(name), b) if name == from => Sub(Ready(number), b),
-            Mult(Wait(name), b) if name == from => Mult(Ready(number), b),
-            Div(Wait(name), b) if name == from => Div(Ready(number), b),
-            Number(_) => unreachable!("monkey already heard enough"),
-            x => x, // check in next match section
-        };
-        let next = match next {
-            Add(a, Wait(name)) if name == from => Add(a, Ready(number)),
-            Sub(a, Wait(name)) if name == from => Sub(a, Ready(number)),
-            Mult(a, Wait(name)) if name == from => Mult(a, Ready(number)),
-            Div(a, Wait(name)) if name == from => Div(a, Ready(number)),
-            Number(_) => unreachable!("monkey already heard enough"),
-            x => x,
-        };
-        *self = match next {
-            Add(Ready(a), Ready(b)) => Number(a + b),
-            Sub(Ready(a), Ready(b)) => Number(a - b),
-            Mult(Ready(a), Ready(b)) => Number(a * b),
-            Div(Ready(a), Ready(b)) => Number(a / b),
-            x => x,
-        };
+    /// Finds the number yelled by you for the node `id` to equal `target`,
+    /// by inverting the operations on the way down to `Human`.
+    fn solve(&self, values: &[Option<i64>], mut id: usize, mut target: i64) -> anyhow::Result<i64> {
+        loop {
+            let (op, a, b) = match self.nodes[id] {
+                Node::Human => return Ok(target),
+                Node::Number(_) => return Err(err!("the expression does not depend on {}", HUMAN)),
+                Node::Op(op, a, b) => (op, a, b),
+            };
+            let unsolvable = || err!("no integer solution for {} in an operation {}", HUMAN, op);
+            let x = match (self.has_human[a], self.has_human[b]) {
+                (true, true) => return Err(err!("{} appears on both sides of an operation {}", HUMAN, op)),
+                // target = x op b
+                (true, false) => {
+                    let b = values[b].expect("known without you");
+                    id = a;
+                    match op {
+                        Op::Add => target.checked_sub(b),
+                        Op::Sub => target.checked_add(b),
+                        Op::Mult => exact_div(target, b),
+                        Op::Div => target.checked_mul(b),
+                    }
+                }
+                // target = a op x
+                (false, true) => {
+                    let a = values[a].expect("known without you");
+                    id = b;
+                    match op {
+                        Op::Add => target.checked_sub(a),
+                        Op::Sub => a.checked_sub(target),
+                        Op::Mult => exact_div(target, a),
+                        Op::Div => exact_div(a, target),
+                    }
+                }
+                (false, false) => return Err(err!("the expression does not depend on {}", HUMAN)),
+            };
+            target = x.ok_or_else(unsolvable)?;
+        }
     }
 }
 
 impl str::FromStr for Yell {
-    type Err = anyhow::Error;
-    fn from_str(value: &str) -> anyhow::Result<Self> {
+    type Err = ParseError;
+    fn from_str(value: &str) -> Result<Self, ParseError> {
         match value.trim().parse::<i64>() {
             Ok(x) => Ok(Yell::Number(x)),
             Err(_) => {
-                let (arg1, tail) = value.split_once(" ").ok_or_else(|| err!("space not found"))?;
-                let (op, arg2) = tail.split_once(" ").ok_or_else(|| err!("space not found"))?;
-                let arg1 = Arg::Wait(arg1.to_string());
-                let arg2 = Arg::Wait(arg2.to_string());
-                match op {
-                    "+" => Ok(Yell::Add(arg1, arg2)),
-                    "-" => Ok(Yell::Sub(arg1, arg2)),
-                    "*" => Ok(Yell::Mult(arg1, arg2)),
-                    "/" => Ok(Yell::Div(arg1, arg2)),
-                    _ => Err(err!("unexpected line {}", value)),
-                }
+                let (arg1, tail) = parser::split(value, value, " ")?;
+                let (op, arg2) = parser::split(value, tail, " ")?;
+                let op = match op {
+                    "+" => Op::Add,
+                    "-" => Op::Sub,
+                    "*" => Op::Mult,
+                    "/" => Op::Div,
+                    _ => return Err(ParseError::at(value, op, "expected operation '+', '-', '*' or '/'")),
+                };
+                Ok(Yell::Op(op, arg1.to_string(), arg2.to_string()))
             }
         }
     }
 }
 
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = match self {
+            Op::Add => '+',
+            Op::Sub => '-',
+            Op::Mult => '*',
+            Op::Div => '/',
+        };
+        write!(f, "{}", op)
+    }
+}
+
+impl fmt::Display for Yell {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Yell::Number(n) => write!(f, "{}", n),
+            Yell::Op(op, a, b) => write!(f, "{} {} {}", a, op, b),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::main;
+    use super::{parse, part1, part2, Yell};
+    use crate::fuzz::{self, Rng};
 
     static DATA: &str = r#"
 root: pppw + sjmn
@@ -159,7 +278,56 @@ hmdt: 32"#;
 
     #[test]
     fn solution() {
-        let res = main(DATA).expect("invalid input");
-        assert_eq!(res, (152, 0));
+        let monkeys = parse(DATA).expect("invalid input");
+        assert_eq!(part1(&monkeys).expect("invalid input"), 152);
+        assert_eq!(part2(&monkeys).expect("invalid input"), 301);
+    }
+
+    #[test]
+    fn unsolvable() {
+        let both_sides = parse("root: humn + abcd\nabcd: humn * humn\nhumn: 5").expect("invalid input");
+        let err = part2(&both_sides).expect_err("humn twice");
+        assert!(err.to_string().contains("both sides"), "{}", err);
+
+        let inexact = parse("root: abcd + efgh\nabcd: humn * zzzz\nzzzz: 2\nefgh: 7\nhumn: 1")
+            .expect("invalid input");
+        let err = part2(&inexact).expect_err("7 is odd");
+        assert!(err.to_string().contains("no integer solution"), "{}", err);
+
+        // every monkey waits twice for the next one, built once each
+        let mut shared: String = (0..40).map(|i| format!("m{}: m{} + m{}\n", i, i + 1, i + 1)).collect();
+        shared += "m40: humn * one\nhumn: 1\nroot: m0 + one\none: 1";
+        let shared = parse(&shared).expect("invalid input");
+        assert_eq!(part1(&shared).expect("invalid input"), (1 << 40) + 1);
+        let err = part2(&shared).expect_err("humn twice");
+        assert!(err.to_string().contains("both sides"), "{}", err);
+    }
+
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
+
+        // a chain too long to recurse over
+        let n = 50_000;
+        let mut chain: String = (0..n).map(|i| format!("m{}: m{} + one\n", i, i + 1)).collect();
+        chain += &format!("m{}: humn * one\nhumn: 5\nroot: m0 - one\none: 1", n);
+        let chain = parse(&chain).expect("invalid input");
+        assert_eq!(part1(&chain).expect("invalid input"), 5 + n - 1);
+        assert_eq!(part2(&chain).expect("invalid input"), 1 - n);
+
+        let cycle = parse("root: abcd + humn\nabcd: efgh * humn\nefgh: abcd - humn\nhumn: 1").expect("invalid input");
+        assert!(part1(&cycle).expect_err("cycle").to_string().contains("waits for its own number"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let generate = |rng: &mut Rng| match rng.below(5) {
+            0 => (rng.next() as i64 >> rng.below(64)).to_string(),
+            _ => {
+                let op = rng.pick(&['+', '-', '*', '/']);
+                format!("{} {} {}", fuzz::word(rng, 4), op, fuzz::word(rng, 4))
+            }
+        };
+        fuzz::round_trip(21, generate, |yell| yell.parse::<Yell>().map(|y| y.to_string()));
     }
 }
@@ -1,24 +1,75 @@
+use std::collections::VecDeque;
+use std::fmt;
 use std::str;
+
 use anyhow::anyhow as err;
 
-pub fn main(data: &str) -> anyhow::Result<(usize, usize)> {
-    let (map, commands) = data.split_once("\n\n").ok_or_else(|| err!("bad input"))?;
+use crate::grid::Grid;
+use crate::parser::ParseError;
+use crate::solution::{Answer, Solution};
+
+pub fn parse(data: &str) -> anyhow::Result<Notes> {
+    let (map, commands) = data.split_once("\n\n").ok_or_else(|| {
+        let lines = data.lines().count();
+        let last = data.lines().last().unwrap_or_default();
+        ParseError::at_end(last, "expected an empty line followed by the path").line(lines)
+    })?;
 
     let map: Map = map.parse()?;
-    let Commands(commands) = commands.parse()?;
+    // the path follows the map and an empty line
+    let path_line = map.tiles.height() + 2;
+    let Commands(commands) = commands.parse().map_err(|e: ParseError| e.line(path_line))?;
+    Ok(Notes { map, commands })
+}
+
+pub fn part1(notes: &Notes) -> anyhow::Result<usize> {
+    let map = &notes.map;
     let mut position = map.start();
-    println!("Map: {}x{}", map.width, map.height);
-    for cmd in commands {
-        match cmd {
-            Command::Forward(x) => position.forward(&map, x),
+    for cmd in &notes.commands {
+        match *cmd {
+            Command::Forward(x) => position.forward(map, x),
             Command::TurnRight => position.turn_right(),
             Command::TurnLeft => position.turn_left(),
         }
     }
+    Ok(position.number())
+}
 
-    let part1 = position.number();
+pub fn part2(notes: &Notes) -> anyhow::Result<usize> {
+    let map = &notes.map;
+    let cube = Cube::fold(map)?;
+    let mut position = map.start();
+    for cmd in &notes.commands {
+        match *cmd {
+            Command::Forward(x) => position.forward_on_cube(map, &cube, x),
+            Command::TurnRight => position.turn_right(),
+            Command::TurnLeft => position.turn_left(),
+        }
+    }
+    Ok(position.number())
+}
+
+pub struct Solver;
+
+impl Solution for Solver {
+    type Input = Notes;
+
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Final password", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Final password on cube", part2(input)?))
+    }
+}
 
-    Ok((part1, 0))
+/// The board map and the path to follow on it.
+#[derive(Debug)]
+pub struct Notes {
+    map: Map,
+    commands: Vec<Command>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -35,7 +86,7 @@ enum Command {
     TurnLeft,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum Direction {
     Right,
     Down,
@@ -43,7 +94,21 @@ enum Direction {
     Up,
 }
 
-#[derive(Debug)]
+impl Direction {
+    const ALL: [Direction; 4] = [Direction::Right, Direction::Down, Direction::Left, Direction::Up];
+
+    /// Change of row and column when moving one tile.
+    fn delta(self) -> (isize, isize) {
+        match self {
+            Direction::Right => (0, 1),
+            Direction::Down => (1, 0),
+            Direction::Left => (0, -1),
+            Direction::Up => (-1, 0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Position {
     row: usize,
     col: usize,
@@ -52,95 +117,230 @@ struct Position {
 
 #[derive(Debug, Clone)]
 struct Map {
-    width: usize,
-    height: usize,
-    data: Vec<Vec<Point>>,
+    tiles: Grid<Point>,
 }
 
 impl Map {
+    fn get(&self, row: usize, col: usize) -> Option<&Point> {
+        self.tiles.get((col, row))
+    }
+
     fn start(&self) -> Position {
         let dir = Direction::Right;
         let row = 0;
-        let col = self.data[row].iter().position(|p| matches!(p, Point::Open))
+        // validated when parsing the map
+        let col = self.tiles.row(row).iter().position(|p| matches!(p, Point::Open))
             .expect("no open tiles on first row");
         Position { row, col, dir }
     }
-    fn iter_direction(&self, x: usize, y: usize, dir: Direction)
-        -> Box<dyn Iterator<Item=(&'_ Point, usize, usize)> + '_>
-    {
-        use Direction::*;
-        match dir {
-            Right => {
-                let row = &self.data[y];
-                let n = row.len();
-                let row = row[x..].iter().chain(&row[..x]);
-                Box::new(
-                    row
-                    .enumerate()
-                    .map(move |(i, p)| (p, (x + i) % n))
-                    .cycle()
-                    .filter(|(p, _)| !matches!(p, Point::Skip))
-                    .map(move |(p, x)| (p, x, y))
-                )
-            },
-            Left => {
-                let row = &self.data[y];
-                let n = row.len();
-                let row = row[x..].iter().chain(&row[..x]);
-                Box::new(
-                    row
-                    .enumerate()
-                    .map(move |(i, p)| (p, (x + i) % n))
-                    .cycle()
-                    .step_by(n - 1) // reverse iterator start at current pos
-                    .filter(|(p, _)| !matches!(p, Point::Skip))
-                    .map(move |(p, x)| (p, x, y))
-                )
+
+    /// Next tile from `pos` in its direction, wrapping around the rows and columns of the board.
+    fn next(&self, pos: &Position) -> Position {
+        let (dr, dc) = pos.dir.delta();
+        let (height, width) = (self.tiles.height() as isize, self.tiles.width() as isize);
+        let (mut row, mut col) = (pos.row as isize, pos.col as isize);
+        loop {
+            row = (row + dr).rem_euclid(height);
+            col = (col + dc).rem_euclid(width);
+            if !matches!(self.tiles[(col as usize, row as usize)], Point::Skip) {
+                return Position { row: row as usize, col: col as usize, dir: pos.dir }
             }
-            Up => {
-                let col = &self.data;
-                let col: Vec<_> = col[y..].iter().chain(&col[0..y]).collect();
-                let n = self.height;
-                Box::new(
-                    col
-                    .into_iter()
-                    .enumerate()
-                    .map(move |(i, p)| (p, (y + i) % n))
-                    .cycle()
-                    .step_by(n - 1)
-                    .filter_map(move |(row, y)| row.get(x).map(move |p| (p, x, y)))
-                    .filter(|(p, _, _)| !matches!(p, Point::Skip))
-                )
+        }
+    }
+
+    /// Number of tiles in the row or column walked through from `pos`.
+    fn lap(&self, pos: &Position) -> usize {
+        let is_tile = |p: &&Point| !matches!(p, Point::Skip);
+        match pos.dir {
+            Direction::Right | Direction::Left => self.tiles.row(pos.row).iter().filter(is_tile).count(),
+            Direction::Down | Direction::Up => self.tiles.column(pos.col).filter(is_tile).count(),
+        }
+    }
+}
+
+/// Vector in space, used to fold the map into a cube.
+type Vector = [i32; 3];
+
+fn neg(v: Vector) -> Vector {
+    [-v[0], -v[1], -v[2]]
+}
+
+fn dot(a: Vector, b: Vector) -> i32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+/// Weighted sum of vectors.
+fn combine(terms: &[(Vector, i32)]) -> Vector {
+    let mut out = [0; 3];
+    for (v, k) in terms {
+        for (o, x) in out.iter_mut().zip(v) {
+            *o += x * k;
+        }
+    }
+    out
+}
+
+/// Face of the cube, with the directions of growing columns and rows on the map once folded.
+#[derive(Debug, Clone, Copy)]
+struct Face {
+    /// Top left tile of the face on the map.
+    row: usize,
+    col: usize,
+    /// Outward normal.
+    normal: Vector,
+    right: Vector,
+    down: Vector,
+}
+
+impl Face {
+    fn vector(&self, dir: Direction) -> Vector {
+        match dir {
+            Direction::Right => self.right,
+            Direction::Down => self.down,
+            Direction::Left => neg(self.right),
+            Direction::Up => neg(self.down),
+        }
+    }
+
+    fn direction(&self, v: Vector) -> Option<Direction> {
+        Direction::ALL.into_iter().find(|&dir| self.vector(dir) == v)
+    }
+
+    /// Face glued to this one along its edge in direction `dir`, as it lies on the map.
+    fn unfold(&self, dir: Direction) -> Face {
+        let Face { normal, right, down, .. } = *self;
+        let (normal, right, down) = match dir {
+            Direction::Right => (right, neg(normal), down),
+            Direction::Left => (neg(right), normal, down),
+            Direction::Down => (down, right, neg(normal)),
+            Direction::Up => (neg(down), right, normal),
+        };
+        Face { normal, right, down, ..*self }
+    }
+}
+
+/// The map folded into a cube, with faces of `size` by `size` tiles.
+#[derive(Debug)]
+struct Cube {
+    size: usize,
+    faces: Vec<Face>,
+}
+
+impl Cube {
+    /// Folds the map, which must be one of the 11 nets of a cube.
+    fn fold(map: &Map) -> anyhow::Result<Cube> {
+        let tiles = map.tiles.as_slice().iter().filter(|p| !matches!(p, Point::Skip)).count();
+        let size = (1..).take_while(|n| 6 * n * n <= tiles).last().unwrap_or(0);
+        if size == 0 || 6 * size * size != tiles {
+            return Err(err!("{} tiles do not cover the 6 faces of a cube", tiles));
+        }
+        // blocks of the map fully covered by tiles
+        let mut blocks = Vec::new();
+        for row in (0..map.tiles.height()).step_by(size) {
+            for col in (0..map.tiles.width()).step_by(size) {
+                let covered = (row..row + size)
+                    .flat_map(|r| (col..col + size).map(move |c| (r, c)))
+                    .filter(|&(r, c)| !matches!(map.get(r, c), None | Some(Point::Skip)))
+                    .count();
+                match covered {
+                    0 => (),
+                    n if n == size * size => blocks.push((row, col)),
+                    _ => return Err(err!("tiles at row {}, column {} do not form a cube face", row + 1, col + 1)),
+                }
             }
-            Down => {
-                let col = &self.data;
-                let col = col[y..].iter().chain(&col[0..y]);
-                let n = self.height;
-                Box::new(
-                    col
-                    .enumerate()
-                    .map(move |(i, p)| (p, (y + i) % n))
-                    .cycle()
-                    .filter_map(move |(row, y)| row.get(x).map(move |p| (p, x, y)))
-                    .filter(|(p, _, _)| !matches!(p, Point::Skip))
-                )
+        }
+
+        // walk over the net folding the faces around the first one
+        let (row, col) = blocks[0];
+        let first = Face { row, col, normal: [0, 0, 1], right: [1, 0, 0], down: [0, 1, 0] };
+        let mut faces = vec![first];
+        let mut todo = VecDeque::from([first]);
+        while let Some(face) = todo.pop_front() {
+            for dir in Direction::ALL {
+                let (dr, dc) = dir.delta();
+                let row = face.row as isize + dr * size as isize;
+                let col = face.col as isize + dc * size as isize;
+                let on_map = |f: &Face| f.row as isize == row && f.col as isize == col;
+                if !blocks.iter().any(|&(r, c)| r as isize == row && c as isize == col)
+                    || faces.iter().any(on_map)
+                {
+                    continue
+                }
+                let next = Face { row: row as usize, col: col as usize, ..face.unfold(dir) };
+                if faces.iter().any(|f| f.normal == next.normal) {
+                    return Err(err!("the map folds two faces onto one side of the cube"));
+                }
+                faces.push(next);
+                todo.push_back(next);
             }
         }
+        if faces.len() != 6 {
+            return Err(err!("the cube faces are not connected"));
+        }
+        Ok(Cube { size, faces })
+    }
+
+    fn face(&self, row: usize, col: usize) -> &Face {
+        self.faces
+            .iter()
+            .find(|f| (f.row..f.row + self.size).contains(&row) && (f.col..f.col + self.size).contains(&col))
+            .expect("position on a face")
+    }
+
+    /// Position one tile ahead, continuing onto the adjacent face across an edge.
+    fn step(&self, pos: &Position) -> Position {
+        let face = self.face(pos.row, pos.col);
+        let (dr, dc) = pos.dir.delta();
+        let (row, col) = (pos.row as isize + dr, pos.col as isize + dc);
+        let n = self.size as isize;
+        let (i, j) = (row - face.row as isize, col - face.col as isize);
+        if (0..n).contains(&i) && (0..n).contains(&j) {
+            return Position { row: row as usize, col: col as usize, dir: pos.dir };
+        }
+
+        // tile center in space, with the cube spanning -n..n on every axis
+        let n = self.size as i32;
+        let (i, j) = ((pos.row - face.row) as i32, (pos.col - face.col) as i32);
+        let center = combine(&[(face.normal, n), (face.right, 2 * j + 1 - n), (face.down, 2 * i + 1 - n)]);
+        // going over the edge turns the direction of travel into the normal of the next face
+        let travel = face.vector(pos.dir);
+        let center = combine(&[(center, 1), (travel, 1), (face.normal, -1)]);
+        let next = self.faces.iter().find(|f| f.normal == travel).expect("cube has all faces");
+        let i = (dot(center, next.down) + n - 1) / 2;
+        let j = (dot(center, next.right) + n - 1) / 2;
+        Position {
+            row: next.row + i as usize,
+            col: next.col + j as usize,
+            dir: next.direction(neg(face.normal)).expect("faces meet at an edge"),
+        }
     }
 }
 
 impl Position {
+    fn forward_on_cube(&mut self, map: &Map, cube: &Cube, steps: usize) {
+        // walking straight ahead comes back to the same tile after going around the cube
+        let lap = 4 * cube.size;
+        let steps = if steps >= lap { lap + steps % lap } else { steps };
+        for _ in 0..steps {
+            let next = cube.step(self);
+            if matches!(map.get(next.row, next.col), Some(Point::Wall)) {
+                break
+            }
+            *self = next;
+        }
+    }
+
     fn forward(&mut self, map: &Map, steps: usize) {
-        if let Some((_, col, row)) = map
-            .iter_direction(self.col, self.row, self.dir)
-            .take_while(|(p, _, _)| !matches!(p, Point::Wall))
-            .take(steps + 1)
-            .last()
-        {
-            self.row = row;
-            self.col = col;
+        // walking straight ahead comes back to the same tile after a whole row or column
+        let lap = map.lap(self);
+        let steps = if steps >= lap { lap + steps % lap } else { steps };
+        for _ in 0..steps {
+            let next = map.next(self);
+            if matches!(map.get(next.row, next.col), Some(Point::Wall)) {
+                break
+            }
+            *self = next;
         }
-        //println!("New row/col: {}/{}", row, col);
     }
 
     fn turn_right(&mut self) {
@@ -172,19 +372,20 @@ impl Position {
 struct Commands(Vec<Command>);
 
 impl str::FromStr for Commands {
-    type Err = anyhow::Error;
-    fn from_str(val: &str) -> anyhow::Result<Self> {
+    type Err = ParseError;
+    fn from_str(val: &str) -> Result<Self, ParseError> {
         let mut out = Vec::new();
-        let mut x = None;
-        for c in val.trim().chars() {
+        let mut x: Option<usize> = None;
+        let val = val.trim_end();
+        for (idx, c) in val.char_indices() {
             match c {
                 '0'..='9' => {
                     let i = (c as u8 - b'0') as usize;
-                    if let Some(x) = x.as_mut() {
-                        *x = *x * 10 + i;
-                    } else {
-                        x = Some(i);
+                    let next = match x {
+                        Some(x) => x.checked_mul(10).and_then(|x| x.checked_add(i)),
+                        None => Some(i),
                     };
+                    x = Some(next.ok_or_else(|| ParseError::at(val, &val[idx..], "number too large"))?);
                 }
                 'R' => {
                     match x.take() {
@@ -200,7 +401,7 @@ impl str::FromStr for Commands {
                     }
                     out.push(Command::TurnLeft);
                 }
-                _ => return Err(err!("unexpected char: {:?}", c))
+                _ => return Err(ParseError::at(val, &val[idx..], "expected a number, 'R' or 'L'"))
             }
         }
         if let Some(x) = x.take() {
@@ -210,29 +411,58 @@ impl str::FromStr for Commands {
     }
 }
 
+impl fmt::Display for Commands {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for cmd in &self.0 {
+            match cmd {
+                Command::Forward(x) => write!(f, "{}", x)?,
+                Command::TurnRight => write!(f, "R")?,
+                Command::TurnLeft => write!(f, "L")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Map {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (idx, row) in self.tiles.rows().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            let line: String = row.iter().map(|p| match p {
+                Point::Skip => ' ',
+                Point::Open => '.',
+                Point::Wall => '#',
+            }).collect();
+            // rows are padded to the width of the board
+            write!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
 impl str::FromStr for Map {
-    type Err = anyhow::Error;
-    fn from_str(val: &str) -> anyhow::Result<Self> {
-        let height = val.lines().count();
-        let width = val.lines().map(|l| l.len()).max().ok_or_else(|| err!("no lines"))?;
-        let mut data = Vec::with_capacity(height);
-        for line in val.lines() {
-            let row: Vec<_> = line.chars().map(|c| match c {
-                ' ' => Ok(Point::Skip),
-                '#' => Ok(Point::Wall),
-                '.' => Ok(Point::Open),
-                _ => Err(err!("unexpected char '{:?}'", c)),
-            }).collect::<anyhow::Result<Vec<_>>>()?;
-            data.push(row);
+    type Err = ParseError;
+    fn from_str(val: &str) -> Result<Self, ParseError> {
+        let tiles = Grid::parse_padded(val, Point::Skip, "expected ' ', '#' or '.'", |c| match c {
+            ' ' => Some(Point::Skip),
+            '#' => Some(Point::Wall),
+            '.' => Some(Point::Open),
+            _ => None,
+        })?;
+        if tiles.height() == 0 || !tiles.row(0).iter().any(|p| matches!(p, Point::Open)) {
+            let first = val.lines().next().unwrap_or_default();
+            return Err(ParseError::at_end(first, "expected an open tile on the first row").line(1));
         }
-        Ok(Map { width, height, data })
+        Ok(Map { tiles })
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::main;
     use super::*;
+    use crate::fuzz::{self, Rng};
 
     static DATA: &str = r#"        ...#
         .#..
@@ -252,8 +482,83 @@ mod test {
 
     #[test]
     fn solution() {
-        let res = main(DATA).expect("invalid input");
-        assert_eq!(res, (6032, 0));
+        let notes = parse(DATA).expect("invalid input");
+        assert_eq!(part1(&notes).expect("invalid input"), 6032);
+        assert_eq!(part2(&notes).expect("invalid input"), 5031);
+    }
+
+    /// All 11 nets of a cube, `#` for faces.
+    static NETS: [&str; 11] = [
+        "#...\n####\n#...",
+        "#...\n####\n.#..",
+        "#...\n####\n..#.",
+        "#...\n####\n...#",
+        ".#..\n####\n.#..",
+        ".#..\n####\n..#.",
+        "##..\n.###\n.#..",
+        "##..\n.###\n..#.",
+        "##..\n.###\n...#",
+        "##..\n.##.\n..##",
+        "###..\n..###",
+    ];
+
+    /// Map of open tiles laid out as `net`, with faces of `size` tiles.
+    fn net_map(net: &str, size: usize) -> Map {
+        let rows: Vec<String> = net
+            .lines()
+            .flat_map(|line| {
+                let row: String = line.chars()
+                    .map(|c| if c == '#' { ".".repeat(size) } else { " ".repeat(size) })
+                    .collect();
+                std::iter::repeat_n(row.trim_end().to_string(), size)
+            })
+            .collect();
+        rows.join("\n").parse().expect("invalid input")
+    }
+
+    #[test]
+    fn cube_edges() {
+        let notes = parse(DATA).expect("invalid input");
+        let cube = Cube::fold(&notes.map).expect("cube net");
+        assert_eq!(cube.size, 4);
+        let a = Position { row: 5, col: 11, dir: Direction::Right };
+        assert_eq!(cube.step(&a), Position { row: 8, col: 14, dir: Direction::Down });
+        let c = Position { row: 11, col: 10, dir: Direction::Down };
+        assert_eq!(cube.step(&c), Position { row: 7, col: 1, dir: Direction::Up });
+    }
+
+    #[test]
+    fn cube_nets() {
+        for net in NETS {
+            let map = net_map(net, 3);
+            let cube = Cube::fold(&map).unwrap_or_else(|e| panic!("{}: {}", e, net));
+            for face in &cube.faces {
+                for (row, col) in (0..3).flat_map(|i| (0..3).map(move |j| (face.row + i, face.col + j))) {
+                    for dir in Direction::ALL {
+                        let start = Position { row, col, dir };
+                        // going around the cube comes back to the start
+                        let mut pos = start;
+                        for _ in 0..4 * cube.size {
+                            pos = cube.step(&pos);
+                        }
+                        assert_eq!(pos, start, "{}", net);
+                        // turning back after a step leads to the same tile
+                        let mut pos = cube.step(&start);
+                        pos.turn_right();
+                        pos.turn_right();
+                        let back = cube.step(&pos);
+                        assert_eq!((back.row, back.col), (row, col), "{}", net);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn not_cube_nets() {
+        assert!(Cube::fold(&net_map("###\n###", 2)).is_err());
+        assert!(Cube::fold(&net_map("#####\n#....", 2)).is_err());
+        assert!(Cube::fold(&"....\n...".parse().expect("invalid input")).is_err());
     }
 
     #[test]
@@ -294,4 +599,37 @@ mod test {
         assert_eq!(start.col, 2);
 
     }
+
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
+    }
+
+    #[test]
+    fn round_trip() {
+        // numbers are separated by turns, otherwise they would merge
+        let commands = |rng: &mut Rng| {
+            let mut path = String::new();
+            for _ in 0..1 + rng.below(8) {
+                if rng.below(4) > 0 {
+                    path += &(1 + (rng.next() >> 1 >> rng.below(63))).to_string();
+                }
+                path.push(*rng.pick(&['R', 'L']));
+            }
+            path + &rng.below(100).to_string()
+        };
+        fuzz::round_trip(22, commands, |path| path.parse::<Commands>().map(|c| c.to_string()));
+
+        let map = |rng: &mut Rng| {
+            let rows: Vec<String> = (0..1 + rng.below(8)).map(|row| {
+                let indent = " ".repeat(rng.below(4));
+                // the first row needs an open tile to start from
+                let first = if row == 0 { "." } else { "" };
+                let tiles: String = (0..1 + rng.below(10)).map(|_| *rng.pick(&['.', '#'])).collect();
+                format!("{}{}{}", indent, first, tiles)
+            }).collect();
+            rows.join("\n")
+        };
+        fuzz::round_trip(22, map, |map| map.parse::<Map>().map(|m| m.to_string()));
+    }
 }
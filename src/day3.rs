@@ -1,28 +1,58 @@
-pub fn main(data: &str) -> anyhow::Result<(usize, usize)> {
-    let part1 = data
-        .lines()
+use crate::parser::{self, ParseError};
+use crate::solution::{Answer, Solution};
+
+pub fn parse(data: &str) -> anyhow::Result<Vec<String>> {
+    let rucksacks = parser::lines(data, |line| {
+        match line.find(|c: char| !c.is_ascii_alphabetic()) {
+            Some(idx) => Err(ParseError::at(line, &line[idx..], "expected item 'a'-'z' or 'A'-'Z'")),
+            None => Ok(line.to_string()),
+        }
+    })?;
+    Ok(rucksacks)
+}
+
+pub fn part1(rucksacks: &[String]) -> anyhow::Result<usize> {
+    let duplicates = rucksacks
+        .iter()
         .filter_map(|line| {
             let n = line.len() / 2;
             let (left, right) = line.split_at(n);
-            left.chars().take(n).filter(|c| right.contains(*c)).next()
+            left.chars().take(n).find(|c| right.contains(*c))
         })
         .collect::<String>();
+    Ok(score(&duplicates))
+}
 
-    let mut lines = data.lines();
-    let mut part2 = 0;
+pub fn part2(rucksacks: &[String]) -> anyhow::Result<usize> {
+    let mut lines = rucksacks.iter();
+    let mut sum = 0;
     while let Some(first) = lines.next() {
         let second = lines.next().ok_or(anyhow::anyhow!("invalid input"))?;
         let third = lines.next().ok_or(anyhow::anyhow!("invalid input"))?;
         let c = first
             .chars()
             .filter(|c| second.contains(*c))
-            .filter(|c| third.contains(*c))
-            .next()
+            .find(|c| third.contains(*c))
             .ok_or(anyhow::anyhow!("No common items found"))?;
-        part2 += char_score(c) as usize;
+        sum += char_score(c) as usize;
     }
+    Ok(sum)
+}
+
+pub struct Solver;
+
+impl Solution for Solver {
+    type Input = Vec<String>;
 
-    Ok((score(&part1), part2))
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Priorities sum of duplicates", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Priorities sum of badges", part2(input)?))
+    }
 }
 
 fn score(s: &str) -> usize {
@@ -39,7 +69,7 @@ fn char_score(c: char) -> u8 {
 
 #[cfg(test)]
 mod test {
-    use super::main;
+    use super::{parse, part1, part2};
 
     static DATA: &str = concat!(
         "vJrwpWtwJgWrhcsFMMfFFhFp\n",
@@ -52,7 +82,13 @@ mod test {
 
     #[test]
     fn solution() {
-        let res = main(DATA).expect("Invalid input");
-        assert_eq!(res, (157, 70));
+        let rucksacks = parse(DATA).expect("Invalid input");
+        assert_eq!(part1(&rucksacks).expect("Invalid input"), 157);
+        assert_eq!(part2(&rucksacks).expect("Invalid input"), 70);
+    }
+
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
     }
 }
@@ -2,23 +2,47 @@ use std::str;
 use std::fmt::Debug;
 use std::cmp;
 
-pub fn main(data: &str) -> anyhow::Result<(usize, usize)> {
-    let mut part1 = 0;
-    let mut part2 = 0;
-    for line in data.lines() {
-        let (range1, range2) = line
-            .split_once(',')
-            .ok_or(anyhow::anyhow!("invalid input"))?;
-        let range1: Range<u64> = range1.parse()?;
-        let range2: Range<u64> = range2.parse()?;
-        if range1.contains(&range2) || range2.contains(&range1) {
-            part1 += 1;
-        }
-        if range1.overlaps(&range2) {
-            part2 += 1;
-        }
+use crate::parser::{self, ParseError};
+use crate::solution::{Answer, Solution};
+
+pub fn parse(data: &str) -> anyhow::Result<Vec<(Range<u64>, Range<u64>)>> {
+    let pairs = parser::lines(data, |line| {
+        let (range1, range2) = parser::split(line, line, ",")?;
+        let range1 = range1.parse().map_err(|e: ParseError| e.within(line, range1))?;
+        let range2 = range2.parse().map_err(|e: ParseError| e.within(line, range2))?;
+        Ok((range1, range2))
+    })?;
+    Ok(pairs)
+}
+
+pub fn part1(pairs: &[(Range<u64>, Range<u64>)]) -> anyhow::Result<usize> {
+    Ok(pairs
+        .iter()
+        .filter(|(range1, range2)| range1.contains(range2) || range2.contains(range1))
+        .count())
+}
+
+pub fn part2(pairs: &[(Range<u64>, Range<u64>)]) -> anyhow::Result<usize> {
+    Ok(pairs
+        .iter()
+        .filter(|(range1, range2)| range1.overlaps(range2))
+        .count())
+}
+
+pub struct Solver;
+
+impl Solution for Solver {
+    type Input = Vec<(Range<u64>, Range<u64>)>;
+
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Fully contained pairs", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Overlapping pairs", part2(input)?))
     }
-    Ok((part1, part2))
 }
 
 #[derive(Debug)]
@@ -28,12 +52,12 @@ pub struct Range<T: Debug> {
 }
 
 impl str::FromStr for Range<u64> {
-    type Err = anyhow::Error;
+    type Err = ParseError;
 
-    fn from_str(val: &str) -> anyhow::Result<Self> {
-        let (start, end) = val.split_once('-').ok_or(anyhow::anyhow!("expected '-'"))?;
-        let start: u64 = start.parse()?;
-        let end: u64 = end.parse()?;
+    fn from_str(val: &str) -> Result<Self, ParseError> {
+        let (start, end) = parser::split(val, val, "-")?;
+        let start: u64 = parser::number(val, start)?;
+        let end: u64 = parser::number(val, end)?;
         Ok(Range { start, end })
     }
 }
@@ -70,7 +94,7 @@ where
 
 #[cfg(test)]
 mod test {
-    use super::main;
+    use super::{parse, part1, part2};
 
     static DATA: &str = r#"2-4,6-8
 2-3,4-5
@@ -81,7 +105,13 @@ mod test {
 
     #[test]
     fn solution() {
-        let res = main(DATA).expect("invalid input");
-        assert_eq!(res, (2, 4));
+        let pairs = parse(DATA).expect("invalid input");
+        assert_eq!(part1(&pairs).expect("invalid input"), 2);
+        assert_eq!(part2(&pairs).expect("invalid input"), 4);
+    }
+
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
     }
 }
@@ -1,57 +1,120 @@
-pub fn main(data: &str) -> anyhow::Result<(String, String)> {
+use std::str;
+
+use crate::parser::{self, ParseError};
+use crate::solution::{Answer, Solution};
+
+pub fn parse(data: &str) -> anyhow::Result<Cargo> {
     let (crates, moves) = data
         .split_once("\n\n")
         .ok_or(anyhow::anyhow!("\n\n was not found"))?;
-    let mut lines = crates.lines().rev();
+    let mut lines: Vec<_> = crates.lines().collect();
     let n = lines
-        .next()
-        .map(|l| l.chars().filter(|c| *c != ' ').count())
+        .pop()
+        .map(|l| l.split_whitespace().count())
         .ok_or(anyhow::anyhow!("could not count number of stacks"))?;
-    assert!(n < 10, "Assumption failed, found more than 9 columns");
     let mut stacks = vec![String::new(); n];
 
-    let crate_indices = lines.flat_map(|line| {
-        (0..n)
-            .filter_map(|idx| line.chars().nth(idx * 4 + 1).map(|c| (idx, c)))
-            .filter(|(_, c)| *c != ' ')
-    });
-
-    for (idx, c) in crate_indices {
-        stacks[idx].push(c);
+    for (lineno, line) in lines.iter().enumerate().rev() {
+        let crates = line.char_indices().skip(1).step_by(4).take(n);
+        for (stack, (idx, c)) in stacks.iter_mut().zip(crates) {
+            match c {
+                ' ' => {}
+                'A'..='Z' => stack.push(c),
+                _ => {
+                    let message = "expected crate label 'A'-'Z'";
+                    return Err(ParseError::at(line, &line[idx..], message).line(lineno + 1).into())
+                }
+            }
+        }
     }
 
-    let mut stacks2 = stacks.clone();
+    // moves follow the crates and an empty line
+    let first_move = crates.lines().count() + 2;
+    let moves = moves
+        .lines()
+        .zip(first_move..)
+        .map(|(line, lineno)| {
+            let m: Move = line.parse().map_err(|e: ParseError| e.line(lineno))?;
+            if m.src >= n || m.dst >= n {
+                let message = format!("expected stacks 1 to {}", n);
+                return Err(ParseError::at(line, line, message).line(lineno))
+            }
+            Ok(m)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
-    for line in moves.lines() {
-        let (count, address) = &line[5..]
-            .split_once(" from ")
-            .ok_or(anyhow::anyhow!("expected ' from ' in move command"))?;
-        let (src, dst) = address
-            .split_once(" to ")
-            .ok_or(anyhow::anyhow!("expected ' to ' in move command"))?;
-        let count: usize = count.parse()?;
-        let src: usize = src.parse::<usize>()? - 1;
-        let dst: usize = dst.parse::<usize>()? - 1;
+    Ok(Cargo { stacks, moves })
+}
 
+pub fn part1(cargo: &Cargo) -> anyhow::Result<String> {
+    let mut stacks = cargo.stacks.clone();
+    for &Move { count, src, dst } in &cargo.moves {
         for _ in 0..count {
             let c = stacks[src].pop().ok_or(anyhow::anyhow!("empty stack"))?;
             stacks[dst].push(c);
         }
+    }
+    Ok(stacks.iter_mut().filter_map(|s| s.pop()).collect())
+}
+
+pub fn part2(cargo: &Cargo) -> anyhow::Result<String> {
+    let mut stacks = cargo.stacks.clone();
+    for &Move { count, src, dst } in &cargo.moves {
+        let x = stacks[src].len().checked_sub(count).ok_or(anyhow::anyhow!("empty stack"))?;
+        let pack: String = stacks[src].drain(x..).collect();
+        stacks[dst].push_str(&pack);
+    }
+    Ok(stacks.iter_mut().filter_map(|s| s.pop()).collect())
+}
+
+pub struct Solver;
 
-        let x = stacks2[src].len() - count;
-        let pack: String = stacks2[src].drain(x..).collect();
-        stacks2[dst].push_str(&pack);
+impl Solution for Solver {
+    type Input = Cargo;
+
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Top crates (CrateMover 9000)", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Top crates (CrateMover 9001)", part2(input)?))
     }
+}
 
-    let part1: String = stacks.iter_mut().filter_map(|s| s.pop()).collect();
-    let part2: String = stacks2.iter_mut().filter_map(|s| s.pop()).collect();
+/// Initial stacks of crates (bottom to top) and the rearrangement procedure.
+#[derive(Debug)]
+pub struct Cargo {
+    stacks: Vec<String>,
+    moves: Vec<Move>,
+}
 
-    Ok((part1, part2))
+#[derive(Debug)]
+struct Move {
+    count: usize,
+    src: usize,
+    dst: usize,
+}
+
+impl str::FromStr for Move {
+    type Err = ParseError;
+    fn from_str(val: &str) -> Result<Self, ParseError> {
+        let rest = parser::prefix(val, val, "move ")?;
+        let (count, address) = parser::split(val, rest, " from ")?;
+        let (src, dst) = parser::split(val, address, " to ")?;
+        let stack = |at| {
+            parser::number::<usize>(val, at)?
+                .checked_sub(1)
+                .ok_or_else(|| ParseError::at(val, at, "stacks are numbered from 1"))
+        };
+        Ok(Move { count: parser::number(val, count)?, src: stack(src)?, dst: stack(dst)? })
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::main;
+    use super::{parse, part1, part2};
 
     static DATA: &str = r#"    [D]    
 [N] [C]    
@@ -65,7 +128,13 @@ move 1 from 1 to 2"#;
 
     #[test]
     fn solution() {
-        let res = main(DATA).expect("invalid input");
-        assert_eq!(res, ("CMZ".to_string(), "MCD".to_string()));
+        let cargo = parse(DATA).expect("invalid input");
+        assert_eq!(part1(&cargo).expect("invalid input"), "CMZ");
+        assert_eq!(part2(&cargo).expect("invalid input"), "MCD");
+    }
+
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
     }
 }
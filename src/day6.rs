@@ -1,21 +1,51 @@
 use anyhow::anyhow as err;
 
-pub fn main(data: &str) -> anyhow::Result<(usize, usize)> {
-    let part1 = find_unique_charset(data.as_bytes(), 4)?;
-    let part2 = find_unique_charset(data.as_bytes(), 14)?;
-    Ok((part1, part2))
+use crate::parser::ParseError;
+use crate::solution::{Answer, Solution};
+
+pub fn parse(data: &str) -> anyhow::Result<String> {
+    let stream = data.trim_end();
+    if let Some(idx) = stream.find(|c: char| !c.is_ascii_lowercase()) {
+        return Err(ParseError::at(stream, &stream[idx..], "expected 'a'-'z'").line(1).into());
+    }
+    Ok(stream.to_string())
+}
+
+pub fn part1(stream: &str) -> anyhow::Result<usize> {
+    find_unique_charset(stream.as_bytes(), 4)
+}
+
+pub fn part2(stream: &str) -> anyhow::Result<usize> {
+    find_unique_charset(stream.as_bytes(), 14)
+}
+
+pub struct Solver;
+
+impl Solution for Solver {
+    type Input = String;
+
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Start-of-packet marker", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Start-of-message marker", part2(input)?))
+    }
 }
 
 fn find_unique_charset(input: &[u8], size: usize) -> anyhow::Result<usize> {
+    if let Some(byte) = input.iter().find(|b| !b.is_ascii_lowercase()) {
+        return Err(err!("invalid char {:?}, not a-z", *byte as char));
+    }
     input
         .windows(size)
         .enumerate()
         .skip_while(|(_, window)| {
             window
                 .iter()
-                .fold(0u32, |mask, byte| {
-                    mask | 1 << byte.checked_sub(b'a').expect("invalid char, not a-z")
-                })
+                .fold(0u32, |mask, byte| mask | 1 << (byte - b'a'))
                 .count_ones() as usize
                 != size
         })
@@ -26,13 +56,18 @@ fn find_unique_charset(input: &[u8], size: usize) -> anyhow::Result<usize> {
 
 #[cfg(test)]
 mod test {
-    use super::main;
+    use super::{part1, part2};
 
     static DATA: &str = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
 
     #[test]
     fn solution() {
-        let res = main(DATA).expect("invalid input");
-        assert_eq!(res, (7, 19));
+        assert_eq!(part1(DATA).expect("invalid input"), 7);
+        assert_eq!(part2(DATA).expect("invalid input"), 19);
+    }
+
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
     }
 }
@@ -1,19 +1,22 @@
 use anyhow::anyhow as err;
 use std::collections::BTreeMap;
+use std::fmt;
 use std::path::PathBuf;
 use std::str;
 
+use crate::parser::{self, ParseError};
+use crate::solution::{Answer, Solution};
+
 const PART1_THRESHOLD: usize = 100_000;
 const TOTAL_SPACE: usize = 70_000_000;
 const NEED_SPACE: usize = 30_000_000;
 
-pub fn main(data: &str) -> anyhow::Result<(usize, usize)> {
+/// Replays the terminal session and returns total size of every directory.
+pub fn parse(data: &str) -> anyhow::Result<BTreeMap<String, usize>> {
     let mut cur_path = PathBuf::new();
     let mut tree = BTreeMap::new();
-    let mut stdout = data.lines().map(Line::from_str);
-    while let Some(line) = stdout.next() {
-        let line = line?;
-        match line {
+    for (idx, line) in data.lines().enumerate() {
+        match Line::from_str(line).map_err(|e| e.line(idx + 1))? {
             Line::Input(Command::Cd(Cd::Root)) => {
                 cur_path.push("/");
             }
@@ -27,25 +30,24 @@ pub fn main(data: &str) -> anyhow::Result<(usize, usize)> {
                 let mut parent = Some(cur_path.as_path());
                 while let Some(p) = parent {
                     parent = p.parent();
-                    tree.entry(p.to_string_lossy().into_owned())
-                        .and_modify(|total| *total += size)
-                        .or_insert(size);
+                    let total = tree.entry(p.to_string_lossy().into_owned()).or_insert(0usize);
+                    *total = total.checked_add(size).ok_or(err!("directory size overflow"))?;
                 }
             }
             _ => {}
         }
     }
+    Ok(tree)
+}
 
-    let mut part1 = 0;
-    for size in tree.values() {
-        if *size <= PART1_THRESHOLD {
-            part1 += size;
-        }
-    }
+pub fn part1(tree: &BTreeMap<String, usize>) -> anyhow::Result<usize> {
+    Ok(tree.values().filter(|&&size| size <= PART1_THRESHOLD).sum())
+}
 
+pub fn part2(tree: &BTreeMap<String, usize>) -> anyhow::Result<usize> {
     let used = tree.get("/").ok_or(err!("missing root"))?;
-    let free = TOTAL_SPACE - used;
-    let need = NEED_SPACE - free;
+    let free = TOTAL_SPACE.checked_sub(*used).ok_or(err!("files do not fit the disk"))?;
+    let need = NEED_SPACE.saturating_sub(free);
 
     let mut min = TOTAL_SPACE;
     for &size in tree.values() {
@@ -53,7 +55,23 @@ pub fn main(data: &str) -> anyhow::Result<(usize, usize)> {
             min = size;
         }
     }
-    Ok((part1, min))
+    Ok(min)
+}
+
+pub struct Solver;
+
+impl Solution for Solver {
+    type Input = BTreeMap<String, usize>;
+
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Small directories total size", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Directory to delete size", part2(input)?))
+    }
 }
 
 #[derive(Debug)]
@@ -77,14 +95,14 @@ enum Cd<'a> {
 
 #[derive(Debug)]
 enum LsOutput<'a> {
-    Dir { name: &'a str },
+    Dir(&'a str),
     File { size: usize, name: &'a str },
 }
 
 impl<'a> Line<'a> {
-    fn from_str(val: &'a str) -> anyhow::Result<Line<'a>> {
-        let res = if val.starts_with("$ ") {
-            Line::Input(Command::from_str(&val[2..])?)
+    fn from_str(val: &'a str) -> Result<Line<'a>, ParseError> {
+        let res = if let Some(cmd) = val.strip_prefix("$ ") {
+            Line::Input(Command::from_str(cmd).map_err(|e| e.within(val, cmd))?)
         } else {
             Line::Output(LsOutput::from_str(val)?)
         };
@@ -93,13 +111,13 @@ impl<'a> Line<'a> {
 }
 
 impl<'a> Command<'a> {
-    fn from_str(val: &'a str) -> anyhow::Result<Command<'a>> {
-        let result = if val.starts_with("cd ") {
-            Command::Cd(Cd::from_str(&val[3..]))
+    fn from_str(val: &'a str) -> Result<Command<'a>, ParseError> {
+        let result = if let Some(dir) = val.strip_prefix("cd ") {
+            Command::Cd(Cd::from_str(dir))
         } else if val == "ls" {
             Command::Ls
         } else {
-            return Err(err!("unexpected command {}", val));
+            return Err(ParseError::at(val, val, "expected 'cd' or 'ls' command"));
         };
         Ok(result)
     }
@@ -116,22 +134,35 @@ impl<'a> Cd<'a> {
 }
 
 impl<'a> LsOutput<'a> {
-    fn from_str(val: &'a str) -> anyhow::Result<LsOutput<'a>> {
-        let res = if val.starts_with("dir ") {
-            let name = &val[4..];
-            LsOutput::Dir { name }
+    fn from_str(val: &'a str) -> Result<LsOutput<'a>, ParseError> {
+        let res = if let Some(name) = val.strip_prefix("dir ") {
+            LsOutput::Dir(name)
         } else {
-            let (size, name) = val.split_once(' ').ok_or(err!("space expected"))?;
-            let size = size.parse()?;
+            let (size, name) = parser::split(val, val, " ")?;
+            let size = parser::number(val, size)?;
             LsOutput::File { size, name }
         };
         Ok(res)
     }
 }
 
+impl<'a> fmt::Display for Line<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Line::Input(Command::Cd(Cd::Root)) => write!(f, "$ cd /"),
+            Line::Input(Command::Cd(Cd::Up)) => write!(f, "$ cd .."),
+            Line::Input(Command::Cd(Cd::Down(name))) => write!(f, "$ cd {}", name),
+            Line::Input(Command::Ls) => write!(f, "$ ls"),
+            Line::Output(LsOutput::Dir(name)) => write!(f, "dir {}", name),
+            Line::Output(LsOutput::File { size, name }) => write!(f, "{} {}", size, name),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::main;
+    use super::{parse, part1, part2, Line};
+    use crate::fuzz::{self, Rng};
 
     static DATA: &str = r#"$ cd /
 $ ls
@@ -159,7 +190,34 @@ $ ls
 
     #[test]
     fn solution() {
-        let res = main(DATA).expect("invalid input");
-        assert_eq!(res, (95437, 24933642));
+        let tree = parse(DATA).expect("invalid input");
+        assert_eq!(part1(&tree).expect("invalid input"), 95437);
+        assert_eq!(part2(&tree).expect("invalid input"), 24933642);
+    }
+
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
+    }
+
+    /// Random file or directory name, possibly with an extension.
+    fn name(rng: &mut Rng) -> String {
+        match rng.below(2) {
+            0 => fuzz::word(rng, 8),
+            _ => format!("{}.{}", fuzz::word(rng, 8), fuzz::word(rng, 3)),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let generate = |rng: &mut Rng| match rng.below(6) {
+            0 => "$ cd /".to_string(),
+            1 => "$ cd ..".to_string(),
+            2 => format!("$ cd {}", name(rng)),
+            3 => "$ ls".to_string(),
+            4 => format!("dir {}", name(rng)),
+            _ => format!("{} {}", rng.next() >> rng.below(64), name(rng)),
+        };
+        fuzz::round_trip(7, generate, |line| Line::from_str(line).map(|l| l.to_string()));
     }
 }
@@ -1,116 +1,57 @@
 use anyhow::anyhow as err;
-use std::str;
 
-pub fn main(data: &str) -> anyhow::Result<(usize, usize)> {
-    let grid: Grid = data.parse()?;
-    let mut part1 = grid.perimeter();
-    part1 += (1..grid.height() - 1)
-        .flat_map(|row| (1..grid.width - 1).map(move |col| (row, col)))
-        .filter(|(row, col)| grid.is_visible(*row, *col))
-        .count();
+use crate::grid::{Grid, Pos, NEIGHBOURS4};
+use crate::parser::ParseError;
+use crate::solution::{Answer, Solution};
 
-    let part2 = (1..grid.height() - 1)
-        .flat_map(|row| (1..grid.width - 1).map(move |col| (row, col)))
-        .map(|(row, col)| grid.view_distance(row, col))
-        .max()
-        .ok_or_else(|| err!("empty iterator"))?;
-    Ok((part1, part2))
+pub fn parse(data: &str) -> anyhow::Result<Grid<u8>> {
+    let grid = Grid::parse(data, "expected a digit", |c| c.to_digit(10).map(|d| d as u8))?;
+    if grid.height() == 0 {
+        return Err(ParseError::at_end(data, "expected a row of trees").line(1).into())
+    }
+    Ok(grid)
 }
 
-#[derive(Debug)]
-struct Grid {
-    width: usize,
-    data: Vec<u8>,
+pub fn part1(grid: &Grid<u8>) -> anyhow::Result<usize> {
+    Ok(grid.iter().filter(|&(at, _)| is_visible(grid, at)).count())
 }
 
-impl Grid {
-    fn height(&self) -> usize {
-        self.data.len() / self.width
-    }
-    fn perimeter(&self) -> usize {
-        (self.width + self.height()) * 2 - 4
-    }
+pub fn part2(grid: &Grid<u8>) -> anyhow::Result<usize> {
+    (1..grid.height() - 1)
+        .flat_map(|y| (1..grid.width() - 1).map(move |x| (x, y)))
+        .map(|at| view_distance(grid, at))
+        .max()
+        .ok_or_else(|| err!("empty iterator"))
+}
 
-    /// Creates two iterators of items to the left and to the right of specified cell,
-    /// excluding the element itself.
-    fn left_right_ray(&self, row: usize, col: usize)
-        -> (impl Iterator<Item=u8> + '_, impl Iterator<Item=u8> + '_)
-    {
-        assert!(row < self.height());
-        assert!(col < self.width);
-        let start = row * self.width;
-        let idx = start + col;
-        let end = start + self.width;
-        (
-            self.data[start..idx].iter().rev().copied(),
-            self.data[idx..end].iter().skip(1).copied(),
-        )
-    }
-    /// Same as left_right_ray but up and down.
-    fn up_down_ray(&self, row: usize, col: usize)
-        -> (impl Iterator<Item=u8> + '_, impl Iterator<Item=u8> + '_)
-    {
-        assert!(row < self.height());
-        assert!(col < self.width);
-        let get_item = move |i| self.data[i * self.width + col];
-        (
-            (0..row).into_iter().rev().map(get_item),
-            (row..self.height()).into_iter().skip(1).map(get_item)
-        )
-    }
+pub struct Solver;
 
-    fn is_visible(&self, row: usize, col: usize) -> bool {
-        assert!(col < self.width);
-        assert!(row < self.height());
-        let idx = row * self.width + col;
-        let val = self.data[idx];
+impl Solution for Solver {
+    type Input = Grid<u8>;
 
-        let (left, right) = self.left_right_ray(row, col);
-        let (up, down) = self.up_down_ray(row, col);
-        [left.max(), right.max(), up.max(), down.max()]
-            .into_iter()
-            .filter_map(|opt_max| opt_max)  // skip `None`s (edges)
-            .any(|max| max < val)
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
     }
-
-    fn view_distance(&self, row: usize, col: usize) -> usize {
-        assert!(col < self.width);
-        assert!(row < self.height());
-        let idx = row * self.width + col;
-        let val = self.data[idx];
-        let (left, right) = self.left_right_ray(row, col);
-        let (up, down) = self.up_down_ray(row, col);
-        [
-            left.count_while_inclusive(|i| i < val),
-            right.count_while_inclusive(|i| i < val),
-            up.count_while_inclusive(|i| i < val),
-            down.count_while_inclusive(|i| i < val),
-        ].into_iter()
-        .product()
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Visible trees", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Best scenic score", part2(input)?))
     }
 }
 
-impl str::FromStr for Grid {
-    type Err = anyhow::Error;
-    fn from_str(val: &str) -> anyhow::Result<Grid> {
-        let mut data = Vec::new();
-        let mut height: usize = 0;
-        let mut width: usize = 0;
-        for c in val.as_bytes() {
-            match c {
-                b'0'..=b'9' => data.push(c - b'0'),
-                b'\n' => {
-                    height += 1;
-                    width = data.len() / height;
-                    if data.len() % height != 0 {
-                        return Err(err!("Unequal rows"))
-                    }
-                }
-                _ => return Err(err!("Unexpected byte {}", c))
-            }
-        }
-        Ok(Grid { width, data })
-    }
+/// Whether all the trees in any direction up to the edge are shorter, trees on the edge are visible.
+fn is_visible(grid: &Grid<u8>, at: Pos) -> bool {
+    let val = grid[at];
+    NEIGHBOURS4.into_iter().any(|step| grid.ray(at, step).all(|(_, &tree)| tree < val))
+}
+
+fn view_distance(grid: &Grid<u8>, at: Pos) -> usize {
+    let val = grid[at];
+    NEIGHBOURS4
+        .into_iter()
+        .map(|step| grid.ray(at, step).count_while_inclusive(|(_, &tree)| tree < val))
+        .product()
 }
 
 /// Extend Iterator trait with extra method.
@@ -121,7 +62,7 @@ trait IteratorExt: Iterator + Sized {
         F: FnMut(Self::Item) -> bool,
     {
         let mut x = 0usize;
-        while let Some(item) = self.next() {
+        for item in self.by_ref() {
             x += 1;
             if !f(item) {
                 break
@@ -138,7 +79,7 @@ where
 
 #[cfg(test)]
 mod test {
-    use super::main;
+    use super::{parse, part1, part2};
 
     static DATA: &str = r#"30373
 25512
@@ -148,7 +89,13 @@ mod test {
 
     #[test]
     fn solution() {
-        let res = main(DATA).expect("invalid input");
-        assert_eq!(res, (21, 8));
+        let grid = parse(DATA).expect("invalid input");
+        assert_eq!(part1(&grid).expect("invalid input"), 21);
+        assert_eq!(part2(&grid).expect("invalid input"), 8);
+    }
+
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
     }
 }
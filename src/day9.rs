@@ -1,47 +1,59 @@
-use anyhow::anyhow as err;
 use std::str;
 use std::f64::consts;
-use std::collections::HashSet;
-
-pub fn main(data: &str) -> anyhow::Result<(usize, usize)> {
-    let mut visited_p1 = HashSet::new();
-    let mut visited_p2 = HashSet::new();
-    let mut head = Knot::default();
-    let mut tail_p1 = Knot::default();
-    let mut knot_2 = Knot::default();
-    let mut knot_3 = Knot::default();
-    let mut knot_4 = Knot::default();
-    let mut knot_5 = Knot::default();
-    let mut knot_6 = Knot::default();
-    let mut knot_7 = Knot::default();
-    let mut knot_8 = Knot::default();
-    let mut tail_p2 = Knot::default();
-    visited_p1.insert(tail_p1.pos);
-    visited_p2.insert(tail_p2.pos);
-    let input = data.lines()
-        .map(|line| {
-                line.parse::<Dt>()
-                .map(|Dt(dir, time)| (0..time).map(move |_| dir))
-                .expect("invalid row")  // ignore error and unwrap
-        })
-        .flatten();
-    for direction in input {
-        head.step(direction);
-        visited_p1.insert(tail_p1.move_towards(&head));
-        knot_2.move_towards(&tail_p1);
-        knot_3.move_towards(&knot_2);
-        knot_4.move_towards(&knot_3);
-        knot_5.move_towards(&knot_4);
-        knot_6.move_towards(&knot_5);
-        knot_7.move_towards(&knot_6);
-        knot_8.move_towards(&knot_7);
-        visited_p2.insert(tail_p2.move_towards(&knot_8));
+
+use crate::grid::SparseGrid;
+use crate::parser::{self, ParseError};
+use crate::solution::{Answer, Solution};
+
+pub fn parse(data: &str) -> anyhow::Result<Vec<Dt>> {
+    Ok(parser::lines(data, str::parse)?)
+}
+
+pub fn part1(moves: &[Dt]) -> anyhow::Result<usize> {
+    Ok(simulate(moves, 2))
+}
+
+pub fn part2(moves: &[Dt]) -> anyhow::Result<usize> {
+    Ok(simulate(moves, 10))
+}
+
+pub struct Solver;
+
+impl Solution for Solver {
+    type Input = Vec<Dt>;
+
+    fn parse(data: &str) -> anyhow::Result<Self::Input> {
+        parse(data)
+    }
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Tail positions (2 knots)", part1(input)?))
+    }
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer> {
+        Ok(Answer::new("Tail positions (10 knots)", part2(input)?))
     }
+}
 
-    Ok((visited_p1.len(), visited_p2.len()))
+/// Moves the rope of `n` knots and counts positions visited by its tail.
+fn simulate(moves: &[Dt], n: usize) -> usize {
+    let mut knots = vec![Knot::default(); n];
+    let mut visited = SparseGrid::new();
+    visited.insert((0, 0), ());
+    let steps = moves
+        .iter()
+        .flat_map(|&Dt(dir, time)| (0..time).map(move |_| dir));
+    for direction in steps {
+        knots[0].step(direction);
+        for i in 1..n {
+            let prev = knots[i - 1];
+            knots[i].move_towards(&prev);
+        }
+        let tail = knots[n - 1].pos;
+        visited.insert((tail.x, tail.y), ());
+    }
+    visited.len()
 }
 
-#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
 struct Position{
     x: i64,
     y: i64,
@@ -64,21 +76,21 @@ enum Direction {
     DownRight,
 }
 
-struct Dt(Direction, u8);
+pub struct Dt(Direction, u8);
 
 impl str::FromStr for Dt {
-    type Err = anyhow::Error;
-    fn from_str(val: &str) -> anyhow::Result<Self> {
+    type Err = ParseError;
+    fn from_str(val: &str) -> Result<Self, ParseError> {
         use Direction::*;
-        let (d, t) = val.split_once(' ').ok_or_else(|| err!("no space in line {}", val))?;
+        let (d, t) = parser::split(val, val, " ")?;
         let d = match d {
             "U" => Up,
             "R" => Right,
             "D" => Down,
             "L" => Left,
-            x => return Err(err!("invalid direction {}", x))
+            x => return Err(ParseError::at(val, x, "expected direction U, R, D or L"))
         };
-        let t = t.parse()?;
+        let t = parser::number(val, t)?;
         Ok(Dt(d, t))
     }
 }
@@ -156,7 +168,7 @@ impl Iterator for DirectionIterator {
 
 #[cfg(test)]
 mod test {
-    use super::main;
+    use super::{parse, part1, part2};
 
     static DATA: &str = r#"R 4
 U 4
@@ -178,12 +190,17 @@ U 20"#;
 
     #[test]
     fn solution1() {
-        let (part1, _) = main(DATA).expect("invalid input");
-        assert_eq!(part1, 13);
+        let moves = parse(DATA).expect("invalid input");
+        assert_eq!(part1(&moves).expect("invalid input"), 13);
     }
     #[test]
     fn solution2() {
-        let (_, part2) = main(DATA2).expect("invalid input");
-        assert_eq!(part2, 36);
+        let moves = parse(DATA2).expect("invalid input");
+        assert_eq!(part2(&moves).expect("invalid input"), 36);
+    }
+
+    #[test]
+    fn malformed() {
+        crate::fuzz::check::<super::Solver>(DATA);
     }
 }
@@ -0,0 +1,114 @@
+//! Known puzzle answers used to verify results on real inputs.
+//!
+//! The file format is one answer per line: `<day> <part> <answer>`, e.g. `5 1 CMZ`.
+//! Empty lines and lines starting with `#` are ignored. Multi-line answers are written
+//! on a single line with `\n` escapes.
+use std::collections::HashMap;
+use std::fmt;
+use std::str;
+
+use anyhow::anyhow as err;
+
+use crate::runner::Outcome;
+use crate::solution::Part;
+
+#[derive(Debug, Default)]
+pub struct Expected {
+    answers: HashMap<(usize, Part), String>,
+}
+
+/// Verification status of a single result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Wrong,
+    Unknown,
+}
+
+impl Expected {
+    pub fn get(&self, day: usize, part: Part) -> Option<&str> {
+        self.answers.get(&(day, part)).map(String::as_str)
+    }
+
+    pub fn check(&self, outcome: &Outcome) -> Status {
+        match (self.get(outcome.day, outcome.part), &outcome.answer) {
+            (None, _) => Status::Unknown,
+            (Some(expected), Ok(answer)) if answer.value.trim_end() == expected => Status::Ok,
+            (Some(_), _) => Status::Wrong,
+        }
+    }
+}
+
+impl str::FromStr for Expected {
+    type Err = anyhow::Error;
+    fn from_str(val: &str) -> anyhow::Result<Self> {
+        let mut answers = HashMap::new();
+        for (idx, line) in val.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+            let lineno = idx + 1;
+            let mut it = line.splitn(3, ' ');
+            let (day, part, answer) = match (it.next(), it.next(), it.next()) {
+                (Some(day), Some(part), Some(answer)) => (day, part, answer),
+                _ => return Err(err!("line {}: expected '<day> <part> <answer>'", lineno)),
+            };
+            let day: usize = day.parse().map_err(|e| err!("line {}: invalid day: {}", lineno, e))?;
+            let part: Part = part.parse().map_err(|e| err!("line {}: {}", lineno, e))?;
+            let answer = answer.trim().replace("\\n", "\n").trim_end().to_string();
+            if answers.insert((day, part), answer).is_some() {
+                return Err(err!("line {}: duplicate answer for day {} part {}", lineno, day, part));
+            }
+        }
+        Ok(Expected { answers })
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Status::Ok => write!(f, "OK"),
+            Status::Wrong => write!(f, "WRONG"),
+            Status::Unknown => write!(f, "UNKNOWN"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Expected, Status};
+    use crate::runner::run;
+    use crate::solution::Part;
+
+    static DATA: &str = r#"
+# day part answer
+1 1 4
+1 2 8
+10 2 ##\n..
+"#;
+
+    #[test]
+    fn parse() {
+        let expected: Expected = DATA.parse().expect("valid answers");
+        assert_eq!(expected.get(1, Part::One), Some("4"));
+        assert_eq!(expected.get(10, Part::Two), Some("##\n.."));
+        assert_eq!(expected.get(2, Part::One), None);
+
+        assert!("1 3 5".parse::<Expected>().is_err());
+        assert!("1 1".parse::<Expected>().is_err());
+        assert!("1 1 2\n1 1 3".parse::<Expected>().is_err());
+    }
+
+    #[test]
+    fn check() {
+        let expected: Expected = DATA.parse().expect("valid answers");
+        let day = crate::day(1).expect("day 1 is registered");
+        assert_eq!(expected.check(&run(day, "1\n2\n\n4\n", Part::One)), Status::Ok);
+        assert_eq!(expected.check(&run(day, "1\n2\n\n4\n", Part::Two)), Status::Wrong);
+        assert_eq!(expected.check(&run(day, "x", Part::Two)), Status::Wrong);
+
+        let day = crate::day(2).expect("day 2 is registered");
+        assert_eq!(expected.check(&run(day, "A Y\n", Part::One)), Status::Unknown);
+    }
+}
@@ -0,0 +1,122 @@
+//! Malformed variants of valid inputs: truncated, randomly edited and random garbage.
+//! Used by the tests of each day to check that bad input results in errors, not panics,
+//! and that generated valid inputs round-trip through the parsers and pretty-printers.
+//!
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::solution::{Part, Solution};
+
+/// Characters mixed into the inputs besides their own, likely to confuse parsers.
+const NOISE: &str = "\n\n  -+,:;.=[]0123456789xyzABC\u{e9}\u{1f600}";
+
+/// Deterministic xorshift generator, so that failures are reproducible.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed | 1)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Random number in `0..n`, `n` must not be 0.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+/// Malformed variants of `example`: `count` truncated, `count` edited and `count` random.
+pub fn variants(example: &str, count: usize) -> Vec<String> {
+    let mut rng = Rng::new(example.len() as u64);
+    let chars: Vec<char> = example.chars().collect();
+    let mut alphabet: Vec<char> = chars.iter().chain(&NOISE.chars().collect::<Vec<_>>()).copied().collect();
+    alphabet.sort();
+    alphabet.dedup();
+
+    let mut out = Vec::with_capacity(count * 3);
+    for _ in 0..count {
+        let len = rng.below(chars.len() + 1);
+        out.push(chars[..len].iter().collect());
+    }
+    for _ in 0..count {
+        let mut edited = chars.clone();
+        let idx = rng.below(edited.len() + 1);
+        match rng.below(4) {
+            0 if idx < edited.len() => {
+                edited.remove(idx);
+            }
+            1 if idx < edited.len() => edited[idx] = *rng.pick(&alphabet),
+            2 => {
+                let end = (idx + 1 + rng.below(16)).min(edited.len());
+                let copy = edited[idx..end].to_vec();
+                edited.splice(idx..idx, copy);
+            }
+            _ => edited.insert(idx, *rng.pick(&alphabet)),
+        }
+        out.push(edited.into_iter().collect());
+    }
+    for _ in 0..count {
+        let len = rng.below(64);
+        out.push((0..len).map(|_| *rng.pick(&alphabet)).collect());
+    }
+    out
+}
+
+/// Runs both parts of `S` on malformed variants of `example`, failing on the first panic.
+pub fn check<S: Solution>(example: &str) {
+    check_parts::<S>(example, &Part::BOTH)
+}
+
+pub fn check_parts<S: Solution>(example: &str, parts: &[Part]) {
+    for data in variants(example, 64) {
+        for &part in parts {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                let input = S::parse(&data)?;
+                match part {
+                    Part::One => S::part1(&input),
+                    Part::Two => S::part2(&input),
+                }
+            }));
+            assert!(result.is_ok(), "part {} panicked on input {:?}", part, data);
+        }
+    }
+}
+
+/// Checks that every generated input parses and prints back unchanged, and that parsing
+/// malformed variants of it does not panic.
+///
+/// `print_parsed` parses the input and pretty-prints the result, which lets it handle
+/// values borrowing from the input.
+pub fn round_trip<F, E>(seed: u64, generate: impl Fn(&mut Rng) -> String, print_parsed: F)
+where
+    F: Fn(&str) -> Result<String, E>,
+    E: fmt::Display,
+{
+    let mut rng = Rng::new(seed);
+    for _ in 0..256 {
+        let input = generate(&mut rng);
+        match print_parsed(&input) {
+            Ok(printed) => assert_eq!(printed, input, "round trip changed the input"),
+            Err(e) => panic!("valid input {:?} rejected: {}", input, e),
+        }
+        for data in variants(&input, 4) {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| print_parsed(&data).is_ok()));
+            assert!(result.is_ok(), "parser panicked on input {:?}", data);
+        }
+    }
+}
+
+/// Random lowercase word of 1 to `max_len` letters.
+pub fn word(rng: &mut Rng, max_len: usize) -> String {
+    (0..1 + rng.below(max_len)).map(|_| (b'a' + rng.below(26) as u8) as char).collect()
+}
@@ -1,8 +1,9 @@
+//! Drawing of maps and animations, in the terminal or in memory.
+//!
+use std::fmt;
 use std::io::{self, Write};
 use std::{time, thread};
 
-pub struct Graphics;
-
 pub type Modifier = &'static str;
 
 pub const HIGHLIGHT: Modifier = "\x1b[1m";
@@ -13,67 +14,274 @@ pub const COLOR_BLUE: Modifier = "\x1b[34m";
 
 const LINES: usize = 160;
 
+/// Pause before each cell drawn by `delay_draw_char`.
+const STEP: time::Duration = time::Duration::from_millis(1);
+/// Pause before each cell of a path drawn slowly by `draw_path`.
+const PATH_STEP: time::Duration = time::Duration::from_millis(10);
+
 const CLR: &str = "\x1bc";
+const RESET: &str = "\x1b[0m";
 
-pub fn draw_map<F, D>(dx: usize, dy: usize, f: F) -> io::Result<()>
+/// Draws `dx` by `dy` cells on a blank screen, `f` gives the cell at each offset.
+pub fn draw_map<F, D>(out: &mut dyn Renderer, dx: usize, dy: usize, f: F) -> io::Result<()>
 where
     F: Fn(usize) -> D,
     D: Drawable,
 {
-    let (dy, y_offset) = if dy <= LINES { (dy, 0) } else { (LINES, dy - LINES) };
-
-    let mut stdout = io::stdout();
-    stdout.write(CLR.as_bytes())?;
+    out.clear(dy)?;
     for y in 0..dy {
         for x in 0..dx {
-            let drawable = f((y + y_offset) * dx + x);
-            write!(stdout, "{}{}\x1b[0m", drawable.modifier(), drawable.symbol())?;
+            let drawable = f(y * dx + x);
+            out.put(x, y, drawable.symbol(), drawable.modifier())?;
         }
-        stdout.write(b"\n")?;
     }
-    stdout.flush()
+    out.flush()
 }
 
-pub fn goto_line(y: usize) -> io::Result<()> {
-    write!(io::stdout(), "\x1b[{}H\n", y)
+/// Ends the drawing, the text that follows goes from row `y` on.
+pub fn goto_line(out: &mut dyn Renderer, y: usize) -> io::Result<()> {
+    out.goto_line(y)
 }
 
-pub fn delay_draw_char<D>(x: usize, y: usize, drawable: D) -> io::Result<()>
+/// Draws one cell after a pause, to animate a map.
+pub fn delay_draw_char<D>(out: &mut dyn Renderer, x: usize, y: usize, drawable: D) -> io::Result<()>
 where
     D: Drawable,
 {
-    //if y < 175 - LINES {
-    //    return Ok(())
-    //}
-    // let y = y - (175 - LINES);
-    let modifier = drawable.modifier();
-    let symbol = drawable.symbol();
-    thread::sleep(time::Duration::from_millis(1));
-    write!(io::stdout(), "\x1b[{};{}H{}{}\x1b[0m",
-        y + 1, x + 1, modifier, symbol)?;
-    io::stdout().flush()
-}
-
-pub fn draw_path<I, F, D>(width: usize, path: I, f: F) -> io::Result<()>
+    out.pause(STEP);
+    out.put(x, y, drawable.symbol(), drawable.modifier())?;
+    out.flush()
+}
+
+/// Draws the cells along `path` on a map `width` cells wide,
+/// one at a time for those whose drawable asks to be flushed.
+pub fn draw_path<I, F, D>(out: &mut dyn Renderer, width: usize, path: I, f: F) -> io::Result<()>
 where
     I: IntoIterator<Item=(usize, usize)>,
     F: Fn(usize) -> D,
     D: Drawable,
 {
-    let mut stdout = io::stdout();
     for (x, y) in path {
-        let offset = y * width + x;
-        let drawable = f(offset);
-        let modifier = drawable.modifier();
-        write!(stdout, "\x1b[{};{}H{}{}\x1b[0m",
-            y + 1, x + 1, modifier, drawable.symbol())?;
+        let drawable = f(y * width + x);
+        if drawable.flush() {
+            out.pause(PATH_STEP);
+        }
+        out.put(x, y, drawable.symbol(), drawable.modifier())?;
         if drawable.flush() {
-            stdout.flush()?;
+            out.flush()?;
+        }
+    }
+    out.flush()
+}
+
+/// Backend drawing cells at `(x, y)` positions of a map, from its top left corner.
+pub trait Renderer {
+    /// Starts drawing a map of `height` rows on a blank screen.
+    fn clear(&mut self, height: usize) -> io::Result<()>;
+
+    /// Draws `symbol` at column `x` of row `y`.
+    fn put(&mut self, x: usize, y: usize, symbol: char, modifier: Modifier) -> io::Result<()>;
+
+    /// Shows what was drawn so far.
+    fn flush(&mut self) -> io::Result<()>;
+
+    /// Waits `duration` before the next step of an animation.
+    fn pause(&mut self, _duration: time::Duration) {}
+
+    /// Ends the drawing, the text that follows goes from row `y` on.
+    fn goto_line(&mut self, y: usize) -> io::Result<()>;
+
+    /// Writes a line of text after the drawing.
+    fn write_line(&mut self, line: &str) -> io::Result<()>;
+}
+
+/// Terminal understanding ANSI escape codes, showing the bottom rows of maps too tall for it.
+pub struct Ansi<W: Write> {
+    out: W,
+    /// Rows shown at most.
+    pub lines: usize,
+    /// Whether animations wait between their steps.
+    pub pauses: bool,
+    /// First row of the map shown.
+    top: usize,
+    /// Where the next character goes without moving the cursor.
+    cursor: Option<(usize, usize)>,
+}
+
+impl<W: Write> Ansi<W> {
+    pub fn new(out: W) -> Self {
+        Ansi { out, lines: LINES, pauses: true, top: 0, cursor: None }
+    }
+}
+
+impl Ansi<io::Stdout> {
+    pub fn stdout() -> Self {
+        Ansi::new(io::stdout())
+    }
+}
+
+impl<W: Write> Renderer for Ansi<W> {
+    fn clear(&mut self, height: usize) -> io::Result<()> {
+        self.top = height.saturating_sub(self.lines);
+        self.cursor = Some((0, self.top));
+        self.out.write_all(CLR.as_bytes())
+    }
+
+    fn put(&mut self, x: usize, y: usize, symbol: char, modifier: Modifier) -> io::Result<()> {
+        if y < self.top {
+            return Ok(())
+        }
+        if self.cursor != Some((x, y)) {
+            write!(self.out, "\x1b[{};{}H", y - self.top + 1, x + 1)?;
+        }
+        self.cursor = Some((x + 1, y));
+        write!(self.out, "{}{}{}", modifier, symbol, RESET)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    fn pause(&mut self, duration: time::Duration) {
+        if self.pauses {
+            thread::sleep(duration);
+        }
+    }
+
+    fn goto_line(&mut self, y: usize) -> io::Result<()> {
+        self.cursor = None;
+        writeln!(self.out, "\x1b[{}H", y.saturating_sub(self.top))?;
+        self.out.flush()
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        self.cursor = None;
+        writeln!(self.out, "{}", line)?;
+        self.out.flush()
+    }
+}
+
+/// Text without escape codes, each drawing is written whole once it is done.
+pub struct Plain<W: Write> {
+    out: W,
+    frame: Frame,
+}
+
+impl<W: Write> Plain<W> {
+    pub fn new(out: W) -> Self {
+        Plain { out, frame: Frame::new() }
+    }
+
+    /// Writes the drawing in progress, if any.
+    fn present(&mut self) -> io::Result<()> {
+        if !self.frame.rows.is_empty() {
+            writeln!(self.out, "{}", self.frame)?;
+            self.frame.rows.clear();
         }
+        self.out.flush()
     }
-    stdout.flush()
 }
 
+impl<W: Write> Drop for Plain<W> {
+    fn drop(&mut self) {
+        // the last drawing may not have been ended
+        self.present().ok();
+    }
+}
+
+impl<W: Write> Renderer for Plain<W> {
+    fn clear(&mut self, height: usize) -> io::Result<()> {
+        self.present()?;
+        self.frame.clear(height)
+    }
+
+    fn put(&mut self, x: usize, y: usize, symbol: char, modifier: Modifier) -> io::Result<()> {
+        self.frame.put(x, y, symbol, modifier)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn goto_line(&mut self, _y: usize) -> io::Result<()> {
+        self.present()
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        self.present()?;
+        writeln!(self.out, "{}", line)?;
+        self.out.flush()
+    }
+}
+
+/// Cells drawn so far kept in memory, blank where nothing was drawn,
+/// and the lines of text written after them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Frame {
+    rows: Vec<Vec<(char, Modifier)>>,
+    text: Vec<String>,
+}
+
+impl Frame {
+    pub fn new() -> Self {
+        Frame { rows: Vec::new(), text: Vec::new() }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<(char, Modifier)> {
+        self.rows.get(y)?.get(x).copied()
+    }
+
+    pub fn text(&self) -> &[String] {
+        &self.text
+    }
+}
+
+impl Renderer for Frame {
+    fn clear(&mut self, height: usize) -> io::Result<()> {
+        self.rows = vec![Vec::new(); height];
+        self.text.clear();
+        Ok(())
+    }
+
+    fn put(&mut self, x: usize, y: usize, symbol: char, modifier: Modifier) -> io::Result<()> {
+        if y >= self.rows.len() {
+            self.rows.resize(y + 1, Vec::new());
+        }
+        let row = &mut self.rows[y];
+        if x >= row.len() {
+            row.resize(x + 1, (' ', ""));
+        }
+        row[x] = (symbol, modifier);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn goto_line(&mut self, _y: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        self.text.push(line.to_string());
+        Ok(())
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (idx, row) in self.rows.iter().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            for (symbol, _) in row {
+                write!(f, "{}", symbol)?;
+            }
+        }
+        Ok(())
+    }
+}
 
 pub trait Drawable {
     fn symbol(&self) -> char;
@@ -99,3 +307,56 @@ impl Drawable for (char, Modifier, bool) {
     fn modifier(&self) -> Modifier { self.1 }
     fn flush(&self) -> bool { self.2 }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{delay_draw_char, draw_map, draw_path, goto_line, Ansi, Frame, Plain, Renderer, HIGHLIGHT};
+
+    /// A 3x2 map, a path over it and an animated cell.
+    fn draw(out: &mut dyn Renderer) {
+        draw_map(out, 3, 2, |offset| (b'a' + offset as u8) as char).expect("drawn");
+        draw_path(out, 3, [(0, 1), (1, 1)], |_| ('*', HIGHLIGHT, true)).expect("drawn");
+        delay_draw_char(out, 2, 0, 'o').expect("drawn");
+        goto_line(out, 2).expect("drawn");
+        out.write_line("end").expect("written");
+    }
+
+    #[test]
+    fn frame() {
+        let mut frame = Frame::new();
+        draw(&mut frame);
+        assert_eq!(frame.to_string(), "abo\n**f");
+        assert_eq!(frame.get(1, 1), Some(('*', HIGHLIGHT)));
+        assert_eq!(frame.get(3, 1), None);
+        assert_eq!(frame.text(), ["end"]);
+        frame.clear(2).expect("cleared");
+        assert_eq!(frame.to_string(), "\n");
+        assert!(frame.text().is_empty());
+    }
+
+    #[test]
+    fn plain() {
+        let mut out = Vec::new();
+        let mut plain = Plain::new(&mut out);
+        draw(&mut plain);
+        // the last drawing is written when done with the renderer
+        draw_map(&mut plain, 1, 1, |_| 'x').expect("drawn");
+        drop(plain);
+        assert_eq!(String::from_utf8(out).expect("utf-8"), "abo\n**f\nend\nx\n");
+    }
+
+    #[test]
+    fn ansi() {
+        let mut out = Vec::new();
+        let mut ansi = Ansi::new(&mut out);
+        ansi.lines = 1;
+        ansi.pauses = false;
+        draw(&mut ansi);
+        let out = String::from_utf8(out).expect("utf-8");
+        // only the bottom row fits, the cells of the top row are not drawn
+        assert!(out.starts_with("\x1bcd\x1b[0me\x1b[0mf\x1b[0m"), "{:?}", out);
+        assert!(out.contains("\x1b[1;1H\x1b[1m*\x1b[0m\x1b[1m*"), "{:?}", out);
+        assert!(!out.contains('o'), "{:?}", out);
+        assert!(out.ends_with("\x1b[1H\nend\n"), "{:?}", out);
+    }
+}
@@ -0,0 +1,434 @@
+//! Grids of cells: rectangular grids stored row after row, and sparse grids
+//! over unbounded coordinates.
+//!
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+use crate::parser::ParseError;
+
+/// Position of a cell as `(x, y)`: its column and its row.
+pub type Pos = (usize, usize);
+
+/// Steps to the 4 orthogonal neighbours, as `(dx, dy)`.
+pub const NEIGHBOURS4: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+/// Steps to the 8 neighbours, diagonals included.
+pub const NEIGHBOURS8: [(isize, isize); 8] = [
+    (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1),
+];
+
+/// Coordinates `(x, y)` of a cell, possibly outside of any grid.
+pub trait Coords: Copy {
+    /// Position of the cell, `None` if a coordinate is negative.
+    fn pos(self) -> Option<Pos>;
+}
+
+impl Coords for Pos {
+    fn pos(self) -> Option<Pos> {
+        Some(self)
+    }
+}
+
+macro_rules! impl_coords {
+    ($($t:ty),*) => {$(
+        impl Coords for ($t, $t) {
+            fn pos(self) -> Option<Pos> {
+                Some((usize::try_from(self.0).ok()?, usize::try_from(self.1).ok()?))
+            }
+        }
+    )*};
+}
+
+impl_coords!(isize, i32, i64);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    pub fn new(width: usize, height: usize, fill: T) -> Self
+    where
+        T: Clone,
+    {
+        Grid { width, height, cells: vec![fill; width * height] }
+    }
+
+    /// Grid of the `cells` laid out in rows of `width`, `None` if they do not fill the last row.
+    pub fn from_cells(width: usize, cells: Vec<T>) -> Option<Self> {
+        match width {
+            0 => cells.is_empty().then_some(Grid { width, height: 0, cells }),
+            _ => (cells.len() % width == 0).then(|| Grid { width, height: cells.len() / width, cells }),
+        }
+    }
+
+    /// Parses a grid of one cell per character, all lines must have the same length.
+    /// `cell` returns `None` for invalid characters, reported with the `expected` message.
+    pub fn parse<F>(data: &str, expected: &str, cell: F) -> Result<Self, ParseError>
+    where
+        F: FnMut(char) -> Option<T>,
+    {
+        let (width, rows) = parse_rows(data, expected, cell)?;
+        let mut cells = Vec::with_capacity(width * rows.len());
+        for (idx, (line, row)) in data.lines().zip(rows).enumerate() {
+            if row.len() != width {
+                let message = format!("expected {} columns like in the first row", width);
+                return Err(ParseError::at_end(line, message).line(idx + 1))
+            }
+            cells.extend(row);
+        }
+        Ok(Grid::from_cells(width, cells).expect("rows of the same width"))
+    }
+
+    /// Like `parse`, lines shorter than the longest one are padded with `fill`.
+    pub fn parse_padded<F>(data: &str, fill: T, expected: &str, cell: F) -> Result<Self, ParseError>
+    where
+        T: Clone,
+        F: FnMut(char) -> Option<T>,
+    {
+        let (_, rows) = parse_rows(data, expected, cell)?;
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let mut cells = Vec::with_capacity(width * rows.len());
+        for row in rows {
+            let padding = width - row.len();
+            cells.extend(row);
+            cells.extend(std::iter::repeat_n(fill.clone(), padding));
+        }
+        Ok(Grid::from_cells(width, cells).expect("rows of the same width"))
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Offset of the cell in `as_slice`, `None` outside of the grid.
+    pub fn offset(&self, at: impl Coords) -> Option<usize> {
+        let (x, y) = at.pos()?;
+        (x < self.width && y < self.height).then(|| y * self.width + x)
+    }
+
+    /// Position of the cell at `offset` in `as_slice`.
+    pub fn pos(&self, offset: usize) -> Pos {
+        (offset % self.width, offset / self.width)
+    }
+
+    pub fn get(&self, at: impl Coords) -> Option<&T> {
+        self.offset(at).map(|offset| &self.cells[offset])
+    }
+
+    pub fn get_mut(&mut self, at: impl Coords) -> Option<&mut T> {
+        self.offset(at).map(|offset| &mut self.cells[offset])
+    }
+
+    /// Cells row after row.
+    pub fn as_slice(&self) -> &[T] {
+        &self.cells
+    }
+
+    /// Cells row after row, with their positions.
+    pub fn iter(&self) -> impl Iterator<Item=(Pos, &T)> {
+        self.cells.iter().enumerate().map(|(offset, cell)| (self.pos(offset), cell))
+    }
+
+    /// Position of the first cell satisfying `predicate`, row after row.
+    pub fn find(&self, predicate: impl FnMut(&T) -> bool) -> Option<Pos> {
+        self.cells.iter().position(predicate).map(|offset| self.pos(offset))
+    }
+
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid { width: self.width, height: self.height, cells: self.cells.iter().map(f).collect() }
+    }
+
+    pub fn row(&self, y: usize) -> &[T] {
+        &self.cells[y * self.width..(y + 1) * self.width]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item=&[T]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    pub fn column(&self, x: usize) -> impl Iterator<Item=&T> {
+        assert!(x < self.width, "column {} out of {}", x, self.width);
+        self.cells.iter().skip(x).step_by(self.width)
+    }
+
+    /// Position one `step` away from `at`, `None` outside of the grid.
+    pub fn step(&self, (x, y): Pos, (dx, dy): (isize, isize)) -> Option<Pos> {
+        let at = (x.checked_add_signed(dx)?, y.checked_add_signed(dy)?);
+        self.offset(at).map(|_| at)
+    }
+
+    /// Cells from `at` in the direction of `step` up to the edge, `at` excluded.
+    pub fn ray(&self, at: Pos, step: (isize, isize)) -> impl Iterator<Item=(Pos, &T)> {
+        std::iter::successors(self.step(at, step), move |&at| self.step(at, step))
+            .map(|at| (at, &self[at]))
+    }
+
+    /// Orthogonal neighbours of `at` in the grid.
+    pub fn neighbours4(&self, at: Pos) -> impl Iterator<Item=Pos> + '_ {
+        NEIGHBOURS4.into_iter().filter_map(move |step| self.step(at, step))
+    }
+
+    /// Neighbours of `at` in the grid, diagonals included.
+    pub fn neighbours8(&self, at: Pos) -> impl Iterator<Item=Pos> + '_ {
+        NEIGHBOURS8.into_iter().filter_map(move |step| self.step(at, step))
+    }
+
+    /// Adds or removes the last rows to have `height` rows, new cells are `fill`.
+    pub fn resize_rows(&mut self, height: usize, fill: T)
+    where
+        T: Clone,
+    {
+        self.cells.resize(height * self.width, fill);
+        self.height = height;
+    }
+
+    /// Removes the first `n` rows, the next rows move up.
+    pub fn remove_rows(&mut self, n: usize) {
+        let n = n.min(self.height);
+        self.cells.drain(..n * self.width);
+        self.height -= n;
+    }
+}
+
+/// Cells of every line, and the number of cells of the first line.
+fn parse_rows<T, F>(data: &str, expected: &str, mut cell: F) -> Result<(usize, Vec<Vec<T>>), ParseError>
+where
+    F: FnMut(char) -> Option<T>,
+{
+    let mut rows = Vec::new();
+    for (idx, line) in data.lines().enumerate() {
+        let row = line.char_indices()
+            .map(|(col, c)| cell(c).ok_or_else(|| ParseError::at(line, &line[col..], expected).line(idx + 1)))
+            .collect::<Result<Vec<_>, _>>()?;
+        rows.push(row);
+    }
+    Ok((rows.first().map_or(0, Vec::len), rows))
+}
+
+impl<T> Index<Pos> for Grid<T> {
+    type Output = T;
+    fn index(&self, at: Pos) -> &T {
+        let offset = self.offset(at)
+            .unwrap_or_else(|| panic!("{:?} out of a {}x{} grid", at, self.width, self.height));
+        &self.cells[offset]
+    }
+}
+
+impl<T> IndexMut<Pos> for Grid<T> {
+    fn index_mut(&mut self, at: Pos) -> &mut T {
+        let offset = self.offset(at)
+            .unwrap_or_else(|| panic!("{:?} out of a {}x{} grid", at, self.width, self.height));
+        &mut self.cells[offset]
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Grid<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (idx, row) in self.rows().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            for cell in row {
+                write!(f, "{}", cell)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Smallest rectangle holding a set of cells, both corners included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds {
+    pub min: (i64, i64),
+    pub max: (i64, i64),
+}
+
+impl Bounds {
+    fn new(at: (i64, i64)) -> Self {
+        Bounds { min: at, max: at }
+    }
+
+    fn extend(&mut self, (x, y): (i64, i64)) {
+        self.min = (self.min.0.min(x), self.min.1.min(y));
+        self.max = (self.max.0.max(x), self.max.1.max(y));
+    }
+
+    pub fn width(&self) -> usize {
+        (self.max.0 - self.min.0) as usize + 1
+    }
+
+    pub fn height(&self) -> usize {
+        (self.max.1 - self.min.1) as usize + 1
+    }
+
+    pub fn contains(&self, (x, y): (i64, i64)) -> bool {
+        (self.min.0..=self.max.0).contains(&x) && (self.min.1..=self.max.1).contains(&y)
+    }
+}
+
+/// Cells at any `(x, y)`, negative coordinates included, only the occupied ones are stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SparseGrid<T> {
+    cells: HashMap<(i64, i64), T>,
+    bounds: Option<Bounds>,
+}
+
+impl<T> SparseGrid<T> {
+    pub fn new() -> Self {
+        SparseGrid { cells: HashMap::new(), bounds: None }
+    }
+
+    /// Number of occupied cells.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Rectangle of the occupied cells, `None` while the grid is empty.
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.bounds
+    }
+
+    pub fn get(&self, at: (i64, i64)) -> Option<&T> {
+        self.cells.get(&at)
+    }
+
+    pub fn get_mut(&mut self, at: (i64, i64)) -> Option<&mut T> {
+        self.cells.get_mut(&at)
+    }
+
+    pub fn contains(&self, at: (i64, i64)) -> bool {
+        self.cells.contains_key(&at)
+    }
+
+    /// Occupies the cell at `at`, returns its previous value.
+    pub fn insert(&mut self, at: (i64, i64), value: T) -> Option<T> {
+        match &mut self.bounds {
+            Some(bounds) => bounds.extend(at),
+            None => self.bounds = Some(Bounds::new(at)),
+        }
+        self.cells.insert(at, value)
+    }
+
+    /// Occupied cells in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item=((i64, i64), &T)> {
+        self.cells.iter().map(|(&at, cell)| (at, cell))
+    }
+
+    /// Dense copy of the occupied window, with the top left corner at `bounds().min`.
+    /// `f` maps every cell, `None` for the free ones.
+    pub fn window<U>(&self, mut f: impl FnMut(Option<&T>) -> U) -> Grid<U> {
+        let Some(bounds) = self.bounds else {
+            return Grid { width: 0, height: 0, cells: Vec::new() }
+        };
+        let cells = (bounds.min.1..=bounds.max.1)
+            .flat_map(|y| (bounds.min.0..=bounds.max.0).map(move |x| (x, y)))
+            .map(|at| f(self.get(at)))
+            .collect();
+        Grid { width: bounds.width(), height: bounds.height(), cells }
+    }
+
+    /// Occupied window drawn row after row from the smallest `y`, one character per cell.
+    pub fn render(&self, f: impl FnMut(Option<&T>) -> char) -> String {
+        self.window(f).to_string()
+    }
+}
+
+impl<T> Default for SparseGrid<T> {
+    fn default() -> Self {
+        SparseGrid::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Bounds, Grid, SparseGrid};
+
+    fn digits(data: &str) -> Grid<u32> {
+        Grid::parse(data, "expected a digit", |c| c.to_digit(10)).expect("invalid input")
+    }
+
+    #[test]
+    fn parse() {
+        let grid = digits("123\n456");
+        assert_eq!((grid.width(), grid.height()), (3, 2));
+        assert_eq!(grid.to_string(), "123\n456");
+        assert_eq!(grid[(2, 1)], 6);
+        assert_eq!(grid.get((-1isize, 0)), None);
+        assert_eq!(grid.get((1i32, 1)), Some(&5));
+        assert_eq!(grid.get((3, 0)), None);
+        assert_eq!(grid.find(|&d| d == 5), Some((1, 1)));
+
+        let err = Grid::parse("12\n345", "expected a digit", |c| c.to_digit(10)).expect_err("ragged");
+        assert_eq!(err.to_string(), "2:4: expected 2 columns like in the first row, found end of line");
+        let err = Grid::parse("12\n3x", "expected a digit", |c| c.to_digit(10)).expect_err("not a digit");
+        assert!(err.to_string().starts_with("2:2: expected a digit"), "{}", err);
+
+        let padded = Grid::parse_padded("1\n234", 0, "expected a digit", |c| c.to_digit(10)).expect("invalid input");
+        assert_eq!(padded.to_string(), "100\n234");
+        assert_eq!(digits("").height(), 0);
+    }
+
+    #[test]
+    fn iterators() {
+        let grid = digits("123\n456\n789");
+        assert_eq!(grid.row(1), [4, 5, 6]);
+        assert_eq!(grid.column(2).copied().collect::<Vec<_>>(), [3, 6, 9]);
+        assert_eq!(grid.rows().count(), 3);
+        let ray = |step| grid.ray((1, 1), step).map(|(_, &d)| d).collect::<Vec<_>>();
+        assert_eq!(ray((1, 0)), [6]);
+        assert_eq!(ray((-1, -1)), [1]);
+        assert_eq!(grid.ray((0, 0), (0, 1)).map(|(at, _)| at).collect::<Vec<_>>(), [(0, 1), (0, 2)]);
+        assert_eq!(grid.neighbours4((0, 0)).collect::<Vec<_>>(), [(1, 0), (0, 1)]);
+        assert_eq!(grid.neighbours4((1, 1)).count(), 4);
+        assert_eq!(grid.neighbours8((2, 2)).collect::<Vec<_>>(), [(1, 2), (1, 1), (2, 1)]);
+        assert_eq!(grid.neighbours8((1, 1)).count(), 8);
+        assert_eq!(grid.iter().nth(5), Some(((2, 1), &6)));
+        assert_eq!(grid.map(|d| d % 2).to_string(), "101\n010\n101");
+    }
+
+    #[test]
+    fn rows() {
+        let mut grid = Grid::new(2, 1, 0);
+        grid[(1, 0)] = 1;
+        grid.resize_rows(3, 7);
+        *grid.get_mut((0, 2)).expect("in the grid") = 2;
+        assert_eq!(grid.to_string(), "01\n77\n27");
+        grid.remove_rows(2);
+        assert_eq!(grid.to_string(), "27");
+        grid.remove_rows(5);
+        assert_eq!(grid.height(), 0);
+        assert!(Grid::from_cells(2, vec![1, 2, 3]).is_none());
+    }
+
+    #[test]
+    fn sparse() {
+        let mut grid = SparseGrid::new();
+        assert_eq!(grid.bounds(), None);
+        assert_eq!(grid.render(|_| '#'), "");
+        assert_eq!(grid.insert((-2, 1), 'a'), None);
+        assert_eq!(grid.insert((1, -1), 'b'), None);
+        assert_eq!(grid.insert((-2, 1), 'c'), Some('a'));
+        assert_eq!(grid.len(), 2);
+        assert_eq!(grid.get((1, -1)), Some(&'b'));
+        assert!(!grid.contains((0, 0)));
+
+        let bounds = grid.bounds().expect("occupied cells");
+        assert_eq!(bounds, Bounds { min: (-2, -1), max: (1, 1) });
+        assert_eq!((bounds.width(), bounds.height()), (4, 3));
+        assert!(bounds.contains((0, 0)) && !bounds.contains((2, 0)));
+        assert_eq!(grid.render(|cell| cell.copied().unwrap_or('.')), "...b\n....\nc...");
+        assert_eq!(grid.window(|cell| cell.is_some()).iter().filter(|(_, &occupied)| occupied).count(), 2);
+    }
+}
@@ -0,0 +1,137 @@
+//! Minimal JSON serialization for machine-readable output.
+//!
+use std::fmt::Write;
+
+pub trait ToJson {
+    fn write_json(&self, out: &mut String);
+
+    fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out);
+        out
+    }
+}
+
+impl ToJson for str {
+    fn write_json(&self, out: &mut String) {
+        out.push('"');
+        for c in self.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                c if (c as u32) < 0x20 => {
+                    write!(out, "\\u{:04x}", c as u32).expect("write to String");
+                }
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+    }
+}
+
+impl ToJson for String {
+    fn write_json(&self, out: &mut String) {
+        self.as_str().write_json(out)
+    }
+}
+
+impl ToJson for bool {
+    fn write_json(&self, out: &mut String) {
+        out.push_str(if *self { "true" } else { "false" });
+    }
+}
+
+macro_rules! impl_number {
+    ($($t:ty),*) => {
+        $(impl ToJson for $t {
+            fn write_json(&self, out: &mut String) {
+                write!(out, "{}", self).expect("write to String");
+            }
+        })*
+    };
+}
+
+impl_number!(u8, u16, u32, u64, u128, usize, i32, i64);
+
+impl<T: ToJson> ToJson for Option<T> {
+    fn write_json(&self, out: &mut String) {
+        match self {
+            Some(value) => value.write_json(out),
+            None => out.push_str("null"),
+        }
+    }
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn write_json(&self, out: &mut String) {
+        out.push('[');
+        for (idx, value) in self.iter().enumerate() {
+            if idx > 0 {
+                out.push(',');
+            }
+            value.write_json(out);
+        }
+        out.push(']');
+    }
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn write_json(&self, out: &mut String) {
+        self.as_slice().write_json(out)
+    }
+}
+
+/// Writes a JSON object field by field.
+pub struct Object<'a> {
+    out: &'a mut String,
+    empty: bool,
+}
+
+impl<'a> Object<'a> {
+    pub fn new(out: &'a mut String) -> Self {
+        out.push('{');
+        Object { out, empty: true }
+    }
+
+    pub fn field<T: ToJson + ?Sized>(mut self, name: &str, value: &T) -> Self {
+        if !self.empty {
+            self.out.push(',');
+        }
+        self.empty = false;
+        name.write_json(self.out);
+        self.out.push(':');
+        value.write_json(self.out);
+        self
+    }
+
+    pub fn finish(self) {
+        self.out.push('}');
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Object, ToJson};
+
+    #[test]
+    fn strings() {
+        assert_eq!("plain".to_json(), r#""plain""#);
+        assert_eq!("a\"b\\c".to_json(), r#""a\"b\\c""#);
+        assert_eq!("#.\n.#\u{1}".to_json(), r##""#.\n.#\u0001""##);
+    }
+
+    #[test]
+    fn objects() {
+        let mut out = String::new();
+        Object::new(&mut out)
+            .field("day", &5usize)
+            .field("answer", "CMZ")
+            .field("error", &None::<String>)
+            .field("samples", &vec![1u64, 2])
+            .finish();
+        assert_eq!(out, r#"{"day":5,"answer":"CMZ","error":null,"samples":[1,2]}"#);
+    }
+}
@@ -0,0 +1,243 @@
+//! Graphs of nodes named by labels, stored as dense integer ids.
+//!
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
+use anyhow::anyhow as err;
+
+use crate::search;
+
+/// Integer type of node ids, `u16` keeps tables small when there are few nodes.
+pub trait Id: Copy + Eq + Hash + fmt::Debug {
+    /// Id of the `index`-th node, `None` if it does not fit.
+    fn from_index(index: usize) -> Option<Self>;
+    fn index(self) -> usize;
+}
+
+macro_rules! impl_id {
+    ($($t:ty),*) => {$(
+        impl Id for $t {
+            fn from_index(index: usize) -> Option<Self> {
+                Self::try_from(index).ok()
+            }
+            fn index(self) -> usize {
+                self as usize
+            }
+        }
+    )*};
+}
+
+impl_id!(u16, u32);
+
+/// Labels numbered in order of first appearance.
+#[derive(Debug, Clone)]
+pub struct Interner<I = u32> {
+    ids: HashMap<String, I>,
+    labels: Vec<String>,
+}
+
+impl<I: Id> Interner<I> {
+    pub fn new() -> Self {
+        Interner { ids: HashMap::new(), labels: Vec::new() }
+    }
+
+    /// Id of `label`, given the next id if it is new.
+    pub fn intern(&mut self, label: &str) -> anyhow::Result<I> {
+        if let Some(&id) = self.ids.get(label) {
+            return Ok(id)
+        }
+        let id = I::from_index(self.labels.len())
+            .ok_or_else(|| err!("too many labels for {} ids", std::any::type_name::<I>()))?;
+        self.ids.insert(label.to_string(), id);
+        self.labels.push(label.to_string());
+        Ok(id)
+    }
+
+    pub fn get(&self, label: &str) -> Option<I> {
+        self.ids.get(label).copied()
+    }
+
+    pub fn label(&self, id: I) -> &str {
+        &self.labels[id.index()]
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+}
+
+impl<I: Id> Default for Interner<I> {
+    fn default() -> Self {
+        Interner::new()
+    }
+}
+
+/// Directed graph with unit length edges between labeled nodes.
+#[derive(Debug, Clone)]
+pub struct Graph<I = u32> {
+    pub labels: Interner<I>,
+    links: Vec<Vec<I>>,
+}
+
+impl<I: Id> Graph<I> {
+    pub fn new() -> Self {
+        Graph { labels: Interner::new(), links: Vec::new() }
+    }
+
+    pub fn add_node(&mut self, label: &str) -> anyhow::Result<I> {
+        let id = self.labels.intern(label)?;
+        if id.index() == self.links.len() {
+            self.links.push(Vec::new());
+        }
+        Ok(id)
+    }
+
+    pub fn add_edge(&mut self, from: &str, to: &str) -> anyhow::Result<()> {
+        let (from, to) = (self.add_node(from)?, self.add_node(to)?);
+        self.links[from.index()].push(to);
+        Ok(())
+    }
+
+    pub fn neighbours(&self, id: I) -> &[I] {
+        &self.links[id.index()]
+    }
+
+    pub fn len(&self) -> usize {
+        self.links.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+
+    /// Steps from `from` to every node, by id, `None` for unreachable nodes.
+    pub fn distances_from(&self, from: I) -> Vec<Option<u32>> {
+        let mut distances = vec![None; self.len()];
+        for (id, steps) in search::bfs_distances([from], |&id: &I| self.neighbours(id).iter().copied()) {
+            // fewer steps than nodes, which are numbered by `u32` at most
+            distances[id.index()] = Some(steps as u32);
+        }
+        distances
+    }
+
+    /// Steps between every pair of nodes, indexed by id.
+    pub fn all_pairs(&self) -> Distances {
+        self.between(&self.ids().collect::<Vec<_>>())
+    }
+
+    /// Graph of the `nodes` only, every one linked to the others by the number of steps
+    /// between them in this graph.
+    pub fn compress(&self, nodes: &[I]) -> Compressed<I> {
+        Compressed { nodes: nodes.to_vec(), distances: self.between(nodes) }
+    }
+
+    fn ids(&self) -> impl Iterator<Item=I> {
+        (0..self.len()).filter_map(I::from_index)
+    }
+
+    /// Steps between the `nodes`, indexed by position in `nodes`.
+    fn between(&self, nodes: &[I]) -> Distances {
+        let mut table = Vec::with_capacity(nodes.len() * nodes.len());
+        for &from in nodes {
+            let distances = self.distances_from(from);
+            table.extend(nodes.iter().map(|to| distances[to.index()]));
+        }
+        Distances { size: nodes.len(), table }
+    }
+}
+
+impl<I: Id> Default for Graph<I> {
+    fn default() -> Self {
+        Graph::new()
+    }
+}
+
+/// Square table of distances, `None` where there is no path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Distances {
+    size: usize,
+    table: Vec<Option<u32>>,
+}
+
+impl Distances {
+    pub fn get(&self, from: usize, to: usize) -> Option<u32> {
+        self.row(from)[to]
+    }
+
+    /// Distances from `from` to every node.
+    pub fn row(&self, from: usize) -> &[Option<u32>] {
+        &self.table[from * self.size..(from + 1) * self.size]
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+}
+
+/// Graph reduced to a subset of nodes.
+#[derive(Debug, Clone)]
+pub struct Compressed<I = u32> {
+    /// Ids of the nodes kept in the original graph.
+    pub nodes: Vec<I>,
+    /// Distances between the nodes kept, indexed by position in `nodes`.
+    pub distances: Distances,
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Graph, Interner};
+
+    /// a <-> b <-> c -> d, and e alone.
+    fn graph() -> Graph<u16> {
+        let mut graph = Graph::new();
+        for (from, to) in [("a", "b"), ("b", "a"), ("b", "c"), ("c", "b"), ("c", "d")] {
+            graph.add_edge(from, to).expect("enough ids");
+        }
+        graph.add_node("e").expect("enough ids");
+        graph
+    }
+
+    #[test]
+    fn interner() {
+        let mut labels = Interner::<u16>::new();
+        assert_eq!(labels.intern("x").expect("enough ids"), 0);
+        assert_eq!(labels.intern("y").expect("enough ids"), 1);
+        assert_eq!(labels.intern("x").expect("enough ids"), 0);
+        assert_eq!(labels.get("y"), Some(1));
+        assert_eq!(labels.get("z"), None);
+        assert_eq!(labels.label(1), "y");
+
+        for i in labels.len()..1 << 16 {
+            labels.intern(&i.to_string()).expect("enough ids");
+        }
+        assert!(labels.intern("one too many").is_err());
+    }
+
+    #[test]
+    fn distances() {
+        let graph = graph();
+        let id = |label| graph.labels.get(label).expect("known label") as usize;
+        let all = graph.all_pairs();
+        assert_eq!(all.len(), 5);
+        assert_eq!(all.get(id("a"), id("d")), Some(3));
+        assert_eq!(all.get(id("d"), id("a")), None);
+        assert_eq!(all.get(id("c"), id("a")), Some(2));
+        assert_eq!(all.get(id("e"), id("e")), Some(0));
+        assert_eq!(all.row(id("e")).iter().flatten().count(), 1);
+
+        let ids = ["d", "a"].map(|label| graph.labels.get(label).expect("known label"));
+        let compressed = graph.compress(&ids);
+        assert_eq!(compressed.nodes, ids);
+        assert_eq!(compressed.distances.row(0), [Some(0), None]);
+        assert_eq!(compressed.distances.row(1), [Some(3), Some(0)]);
+    }
+}
@@ -1,5 +1,16 @@
 pub mod graph;
+pub mod grid;
 pub mod bms;
+pub mod search;
+pub mod labeled;
+pub mod solution;
+pub mod parser;
+#[cfg(test)]
+mod fuzz;
+pub mod runner;
+pub mod expected;
+pub mod json;
+pub mod bench;
 
 pub mod day1;
 pub mod day2;
@@ -23,3 +34,36 @@ pub mod day19;
 pub mod day20;
 pub mod day21;
 pub mod day22;
+
+use solution::Day;
+
+/// All implemented days, ordered by day number.
+pub static DAYS: &[Day] = &[
+    Day::new::<day1::Solver>(1),
+    Day::new::<day2::Solver>(2),
+    Day::new::<day3::Solver>(3),
+    Day::new::<day4::Solver>(4),
+    Day::new::<day5::Solver>(5),
+    Day::new::<day6::Solver>(6),
+    Day::new::<day7::Solver>(7),
+    Day::new::<day8::Solver>(8),
+    Day::new::<day9::Solver>(9),
+    Day::new::<day10::Solver>(10),
+    Day::new::<day11::Solver>(11),
+    Day::new::<day12::Solver>(12),
+    Day::new::<day13::Solver>(13),
+    Day::new::<day14::Solver>(14),
+    Day::new::<day15::Solver>(15),
+    Day::new::<day16::Solver>(16),
+    Day::new::<day17::Solver>(17),
+    Day::new::<day18::Solver>(18),
+    Day::new::<day19::Solver>(19),
+    Day::new::<day20::Solver>(20),
+    Day::new::<day21::Solver>(21),
+    Day::new::<day22::Solver>(22),
+];
+
+/// Looks up a registered day by its number.
+pub fn day(number: usize) -> Option<&'static Day> {
+    DAYS.iter().find(|d| d.number == number)
+}
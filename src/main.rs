@@ -1,148 +1,307 @@
 use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process;
 
-use advent2022::{day1, day2, day3, day4, day5, day6, day7, day8, day9, day10, day11, day12, day13};
-use advent2022::{day14, day15, day16, day17, day18, day19, day20, day21, day22};
-
-static DAY1: &str = include_str!("./input/day01.txt");
-static DAY2: &str = include_str!("./input/day02.txt");
-static DAY3: &str = include_str!("./input/day03.txt");
-static DAY4: &str = include_str!("./input/day04.txt");
-static DAY5: &str = include_str!("./input/day05.txt");
-static DAY6: &str = include_str!("./input/day06.txt");
-static DAY7: &str = include_str!("./input/day07.txt");
-static DAY8: &str = include_str!("./input/day08.txt");
-static DAY9: &str = include_str!("./input/day09.txt");
-static DAY10: &str = include_str!("./input/day10.txt");
-static DAY11: &str = include_str!("./input/day11.txt");
-static DAY12: &str = include_str!("./input/day12.txt");
-static DAY13: &str = include_str!("./input/day13.txt");
-static DAY14: &str = include_str!("./input/day14.txt");
-static DAY15: &str = include_str!("./input/day15.txt");
-static DAY16: &str = include_str!("./input/day16.txt");
-static DAY17: &str = include_str!("./input/day17.txt");
-static DAY18: &str = include_str!("./input/day18.txt");
-static DAY19: &str = include_str!("./input/day19.txt");
-static DAY20: &str = include_str!("./input/day20.txt");
-static DAY21: &str = include_str!("./input/day21.txt");
-static DAY22: &str = include_str!("./input/day22.txt");
+use anyhow::anyhow as err;
 
-fn main() -> anyhow::Result<()> {
-    let day = env::args()
-        .nth(1)
-        .ok_or(anyhow::anyhow!("Day number is required"))
-        .and_then(|s| s.trim().parse().map_err(|e| anyhow::anyhow!("{}", e)))?;
-    match day {
-        1 => {
-            let (answer1, answer2) = day1::main(DAY1)?;
-            println!("#1: Max calories: {}", answer1);
-            println!("#2: Top 3 sum: {}", answer2);
-        }
-        2 => {
-            let (answer1, answer2) = day2::main(DAY2)?;
-            println!("#1: Guessed score: {}", answer1);
-            println!("#2: Real score: {}", answer2);
-        }
-        3 => {
-            let (answer1, answer2) = day3::main(DAY3)?;
-            println!("#1: Priorities sum of duplicates: {}", answer1);
-            println!("#2: Priorities sum of badges: {}", answer2);
-        }
-        4 => {
-            let (answer1, answer2) = day4::main(DAY4)?;
-            println!("#1: fully contains: {}", answer1);
-            println!("#2: overlaps: {}", answer2);
-        }
-        5 => {
-            let (answer1, answer2) = day5::main(DAY5)?;
-            println!("#1: fully contains: {}", answer1);
-            println!("#2: overlaps: {}", answer2);
-        }
-        6 => {
-            let (answer1, answer2) = day6::main(DAY6)?;
-            println!("#1: index: {}", answer1);
-            println!("#2: {}", answer2);
-        }
-        7 => {
-            let (answer1, answer2) = day7::main(DAY7)?;
-            println!("#1: {}", answer1);
-            println!("#2: {}", answer2);
-        }
-        8 => {
-            let (answer1, answer2) = day8::main(DAY8)?;
-            println!("#1: {}", answer1);
-            println!("#2: {}", answer2);
-        }
-        9 => {
-            let (answer1, answer2) = day9::main(DAY9)?;
-            println!("#1: {}", answer1);
-            println!("#2: {}", answer2);
-        }
-        10 => {
-            let (answer1, answer2) = day10::main(DAY10)?;
-            println!("#1: {}", answer1);
-            println!("#2:\n{}", answer2);
-        }
-        11 => {
-            let (answer1, answer2) = day11::main(DAY11)?;
-            println!("#1: {}", answer1);
-            println!("#2: {}", answer2);
-        }
-        12 => {
-            let (answer1, answer2) = day12::main(DAY12)?;
-            println!("#1: {}", answer1);
-            println!("#2: {}", answer2);
-        }
-        13 => {
-            let (answer1, answer2) = day13::main(DAY13)?;
-            println!("#1: {}", answer1);
-            println!("#2: {}", answer2);
-        }
-        14 => {
-            let (answer1, answer2) = day14::main(DAY14)?;
-            println!("#1: {}", answer1);
-            println!("#2: {}", answer2);
-        }
-        15 => {
-            let (answer1, answer2) = day15::main(DAY15)?;
-            println!("#1: {}", answer1);
-            println!("#2: {}", answer2);
+use advent2022::bench::{self, Report};
+use advent2022::expected::{Expected, Status};
+use advent2022::graph;
+use advent2022::json::ToJson;
+use advent2022::runner::{self, Job, Records, Table};
+use advent2022::solution::{Day, Part};
+
+#[cfg(feature = "embed-inputs")]
+mod embedded {
+    static DAYS: [&str; 22] = [
+        include_str!("./input/day01.txt"),
+        include_str!("./input/day02.txt"),
+        include_str!("./input/day03.txt"),
+        include_str!("./input/day04.txt"),
+        include_str!("./input/day05.txt"),
+        include_str!("./input/day06.txt"),
+        include_str!("./input/day07.txt"),
+        include_str!("./input/day08.txt"),
+        include_str!("./input/day09.txt"),
+        include_str!("./input/day10.txt"),
+        include_str!("./input/day11.txt"),
+        include_str!("./input/day12.txt"),
+        include_str!("./input/day13.txt"),
+        include_str!("./input/day14.txt"),
+        include_str!("./input/day15.txt"),
+        include_str!("./input/day16.txt"),
+        include_str!("./input/day17.txt"),
+        include_str!("./input/day18.txt"),
+        include_str!("./input/day19.txt"),
+        include_str!("./input/day20.txt"),
+        include_str!("./input/day21.txt"),
+        include_str!("./input/day22.txt"),
+    ];
+
+    pub fn input(day: usize) -> Option<&'static str> {
+        day.checked_sub(1).and_then(|idx| DAYS.get(idx)).copied()
+    }
+}
+
+const USAGE: &str = "\
+usage: advent <days> [options]
+       advent bench <days> [options] [bench options]
+
+  <days>             day number, range or list: 5, 1-10, 1-3,7 or all
+
+options:
+  -i, --input PATH   read input from PATH, `-` reads stdin (single day only)
+  -                  same as `--input -`
+  -d, --input-dir D  read input of each day from D/dayNN.txt
+  -p, --part 1|2     run only one part
+  -j, --parallel     run selected days in parallel
+  -e, --expected F   verify answers against known answers in F, lines of `<day> <part> <answer>`
+  -f, --format F     output format: `text` (default) or `json`, one record per line
+      --visualize    animate the solution in the terminal (single day only)
+      --renderer R   how --visualize draws: `ansi` (default) or `plain` text without escape codes
+
+bench options:
+  -n, --iterations N measured runs of each part, default 10
+  -w, --warmup N     unmeasured runs before measuring, default 1
+      --json PATH    also write results as JSON to PATH, `-` replaces the table on stdout";
+
+/// Where to read the puzzle input from.
+enum Input {
+    File(String),
+    Stdin,
+    Dir(String),
+    Embedded,
+}
+
+struct Args {
+    days: Vec<&'static Day>,
+    input: Input,
+    parts: Vec<Part>,
+    parallel: bool,
+    visualize: bool,
+    renderer: Renderer,
+    expected: Option<Expected>,
+    format: Format,
+    bench: Option<Bench>,
+}
+
+/// How `--visualize` draws.
+enum Renderer {
+    Ansi,
+    Plain,
+}
+
+/// How answers are printed.
+enum Format {
+    Text,
+    Json,
+}
+
+/// Settings of the `bench` subcommand.
+struct Bench {
+    config: bench::Config,
+    json: Option<String>,
+}
+
+impl Args {
+    fn parse<I>(args: I) -> anyhow::Result<Args>
+    where
+        I: IntoIterator<Item=String>,
+    {
+        let mut days = None;
+        let mut input = Input::Embedded;
+        let mut parts = Part::BOTH.to_vec();
+        let mut parallel = false;
+        let mut visualize = false;
+        let mut expected = None;
+        let mut format = Format::Text;
+        let mut renderer = Renderer::Ansi;
+        let mut config = bench::Config::default();
+        let mut json = None;
+        let mut it = args.into_iter().peekable();
+        let bench = it.next_if_eq("bench").is_some();
+        while let Some(arg) = it.next() {
+            match arg.as_str() {
+                "--iterations" | "-n" if bench => {
+                    let n = it.next().ok_or_else(|| err!("--iterations requires a number\n{}", USAGE))?;
+                    config.iterations = match n.parse() {
+                        Ok(0) => return Err(err!("invalid iteration count {:?}: at least one run is measured", n)),
+                        Ok(n) => n,
+                        Err(e) => return Err(err!("invalid iteration count {:?}: {}", n, e)),
+                    };
+                }
+                "--warmup" | "-w" if bench => {
+                    let n = it.next().ok_or_else(|| err!("--warmup requires a number\n{}", USAGE))?;
+                    config.warmup = n.parse()
+                        .map_err(|e| err!("invalid warm-up count {:?}: {}", n, e))?;
+                }
+                "--json" if bench => {
+                    json = Some(it.next().ok_or_else(|| err!("--json requires a path\n{}", USAGE))?);
+                }
+                "-" => input = Input::Stdin,
+                "--input" | "-i" => {
+                    input = match it.next().as_deref() {
+                        Some("-") => Input::Stdin,
+                        Some(path) => Input::File(path.to_string()),
+                        None => return Err(err!("--input requires a path\n{}", USAGE)),
+                    };
+                }
+                "--input-dir" | "-d" => {
+                    let dir = it.next().ok_or_else(|| err!("--input-dir requires a path\n{}", USAGE))?;
+                    input = Input::Dir(dir);
+                }
+                "--part" | "-p" => {
+                    let part = it.next().ok_or_else(|| err!("--part requires 1 or 2\n{}", USAGE))?;
+                    parts = vec![part.parse()?];
+                }
+                "--expected" | "-e" => {
+                    let path = it.next().ok_or_else(|| err!("--expected requires a path\n{}", USAGE))?;
+                    let data = fs::read_to_string(&path)
+                        .map_err(|e| err!("could not read {}: {}", path, e))?;
+                    let answers = data.parse()
+                        .map_err(|e| err!("{}: {:#}", path, e))?;
+                    expected = Some(answers);
+                }
+                "--format" | "-f" => {
+                    format = match it.next().as_deref() {
+                        Some("text") => Format::Text,
+                        Some("json") => Format::Json,
+                        Some(x) => return Err(err!("invalid format {:?}, expected text or json", x)),
+                        None => return Err(err!("--format requires text or json\n{}", USAGE)),
+                    };
+                }
+                "--renderer" => {
+                    renderer = match it.next().as_deref() {
+                        Some("ansi") => Renderer::Ansi,
+                        Some("plain") => Renderer::Plain,
+                        Some(x) => return Err(err!("invalid renderer {:?}, expected ansi or plain", x)),
+                        None => return Err(err!("--renderer requires ansi or plain\n{}", USAGE)),
+                    };
+                }
+                "--parallel" | "-j" => parallel = true,
+                "--visualize" => visualize = true,
+                "--help" | "-h" => return Err(err!("{}", USAGE)),
+                x if days.is_none() => days = Some(runner::select_days(x)?),
+                x => return Err(err!("unexpected argument {:?}\n{}", x, USAGE)),
+            }
         }
-        16 => {
-            let (answer1, answer2) = day16::main(DAY16)?;
-            println!("#1: {}", answer1);
-            println!("#2: {}", answer2);
+        let days = days.ok_or_else(|| err!("Day number is required\n{}", USAGE))?;
+        let single = matches!(input, Input::File(_) | Input::Stdin);
+        if (single || visualize) && days.len() != 1 {
+            return Err(err!("--input and --visualize require a single day\n{}", USAGE));
         }
-        17 => {
-            let (answer1, answer2) = day17::main(DAY17)?;
-            println!("#1: {}", answer1);
-            println!("#2: {}", answer2);
+        let bench = bench.then_some(Bench { config, json });
+        Ok(Args { days, input, parts, parallel, visualize, renderer, expected, format, bench })
+    }
+
+    fn read_input(&self, day: usize) -> anyhow::Result<String> {
+        match &self.input {
+            Input::File(path) => fs::read_to_string(path)
+                .map_err(|e| err!("could not read {}: {}", path, e)),
+            Input::Stdin => {
+                let mut buf = String::new();
+                io::stdin().read_to_string(&mut buf)?;
+                Ok(buf)
+            }
+            Input::Dir(dir) => {
+                let path = Path::new(dir).join(format!("day{:02}.txt", day));
+                fs::read_to_string(&path)
+                    .map_err(|e| err!("could not read {}: {}", path.display(), e))
+            }
+            #[cfg(feature = "embed-inputs")]
+            Input::Embedded => embedded::input(day)
+                .map(str::to_string)
+                .ok_or_else(|| err!("no embedded input for day {}", day)),
+            #[cfg(not(feature = "embed-inputs"))]
+            Input::Embedded => Err(err!(
+                "no input given for day {}: pass --input PATH, --input-dir DIR or -", day)),
         }
-        18 => {
-            let (answer1, answer2) = day18::main(DAY18)?;
-            println!("#1: {}", answer1);
-            println!("#2: {}", answer2);
+    }
+}
+
+/// Benchmarks selected days one after another, so that runs do not disturb each other.
+fn run_bench(args: &Args, bench: &Bench) -> anyhow::Result<()> {
+    let mut results = Vec::new();
+    for day in &args.days {
+        let data = args.read_input(day.number);
+        for &part in &args.parts {
+            let result = match &data {
+                Ok(data) => bench::bench(day, data, part, &bench.config),
+                Err(e) => bench::Bench { day: day.number, part, result: Err(err!("{:#}", e)) },
+            };
+            results.push(result);
         }
-        19 => {
-            let (answer1, answer2) = day19::main(DAY19)?;
-            println!("#1: {}", answer1);
-            println!("#2: {}", answer2);
+    }
+    match bench.json.as_deref() {
+        Some("-") => println!("{}", Report { config: bench.config, results: &results }.to_json()),
+        Some(path) => {
+            let json = Report { config: bench.config, results: &results }.to_json();
+            fs::write(path, json + "\n").map_err(|e| err!("could not write {}: {}", path, e))?;
+            print!("{}", bench::Table(&results));
         }
-        20 => {
-            let (answer1, answer2) = day20::main(DAY20)?;
-            println!("#1: {}", answer1);
-            println!("#2: {}", answer2);
+        None => print!("{}", bench::Table(&results)),
+    }
+    if results.iter().any(|r| r.result.is_err()) {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse(env::args().skip(1))?;
+    if let Some(bench) = &args.bench {
+        return run_bench(&args, bench);
+    }
+    if let [day] = args.days[..] {
+        if args.visualize {
+            let data = args.read_input(day.number)?;
+            return match args.renderer {
+                Renderer::Ansi => day.visualize(&data, &mut graph::Ansi::stdout()),
+                Renderer::Plain => day.visualize(&data, &mut graph::Plain::new(io::stdout())),
+            };
         }
-        21 => {
-            let (answer1, answer2) = day21::main(DAY21)?;
-            println!("#1: {}", answer1);
-            println!("#2: {}", answer2);
+    }
+    if let ([day], Format::Text) = (&args.days[..], &args.format) {
+        let data = args.read_input(day.number)?;
+        let mut wrong = false;
+        for &part in &args.parts {
+            let outcome = runner::run(day, &data, part);
+            let answer = outcome.answer.as_ref().map_err(|e| err!("{:#}", e))?;
+            match &args.expected {
+                Some(expected) => {
+                    let status = expected.check(&outcome);
+                    wrong |= status == Status::Wrong;
+                    println!("#{}: {} [{}]", part, answer, status);
+                }
+                None => println!("#{}: {}", part, answer),
+            }
         }
-        22 => {
-            let (answer1, answer2) = day22::main(DAY22)?;
-            println!("#1: {}", answer1);
-            println!("#2: {}", answer2);
+        if wrong {
+            process::exit(1);
         }
-        _ => unimplemented!(),
+        return Ok(());
+    }
+
+    let inputs: Vec<_> = args.days.iter().map(|d| args.read_input(d.number)).collect();
+    let jobs: Vec<_> = args.days
+        .iter()
+        .zip(&inputs)
+        .flat_map(|(day, input)| args.parts.iter().map(move |&part| Job { day, part, input }))
+        .collect();
+    let outcomes = runner::run_all(&jobs, args.parallel);
+    match (&args.format, &args.expected) {
+        (Format::Text, None) => println!("{}", Table::new(&outcomes)),
+        (Format::Text, Some(expected)) => println!("{}", Table::new(&outcomes).with_expected(expected)),
+        (Format::Json, None) => println!("{}", Records::new(&outcomes)),
+        (Format::Json, Some(expected)) => println!("{}", Records::new(&outcomes).with_expected(expected)),
+    }
+    let wrong = args.expected
+        .as_ref()
+        .is_some_and(|e| outcomes.iter().any(|o| e.check(o) == Status::Wrong));
+    if wrong || outcomes.iter().any(|o| !o.is_ok()) {
+        process::exit(1);
     }
     Ok(())
 }
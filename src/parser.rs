@@ -0,0 +1,152 @@
+//! Parse errors pointing at the offending place in the puzzle input, and small helpers
+//! for hand-rolled line parsers that produce them.
+//!
+//! Helpers take the whole input `line` next to the slice `at` being parsed, so that the
+//! column can be computed from the position of `at` within `line`.
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Longest part of the input kept in the error.
+const SNIPPET_LEN: usize = 24;
+
+/// Malformed input, displayed as `day19:3:42: expected ' and ', found "or 2 clay"`.
+///
+/// The day and the line are filled in by the callers that know them, so both may be unset
+/// when a single line is parsed on its own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub day: Option<usize>,
+    /// 1-based line number, 0 if unknown.
+    pub line: usize,
+    /// 1-based column, counted in characters.
+    pub column: usize,
+    /// Input starting at the error position, empty at the end of line.
+    pub snippet: String,
+    pub message: String,
+}
+
+impl ParseError {
+    /// Error at the start of `at`, which must be a slice of `line` (otherwise column 1).
+    pub fn at<S: Into<String>>(line: &str, at: &str, message: S) -> Self {
+        ParseError {
+            day: None,
+            line: 0,
+            column: column(line, at),
+            snippet: at.chars().take(SNIPPET_LEN).collect(),
+            message: message.into(),
+        }
+    }
+
+    /// Error at the end of `line`, when more input was expected.
+    pub fn at_end<S: Into<String>>(line: &str, message: S) -> Self {
+        ParseError::at(line, &line[line.len()..], message)
+    }
+
+    /// Sets the line number, unless already known.
+    pub fn line(mut self, line: usize) -> Self {
+        if self.line == 0 {
+            self.line = line;
+        }
+        self
+    }
+
+    pub fn day(mut self, day: usize) -> Self {
+        self.day = Some(day);
+        self
+    }
+
+    /// Makes the column of an error found while parsing `part` relative to `line`,
+    /// a longer slice containing `part`.
+    pub fn within(mut self, line: &str, part: &str) -> Self {
+        self.column += column(line, part) - 1;
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(day) = self.day {
+            write!(f, "day{}:", day)?;
+        }
+        if self.line > 0 {
+            write!(f, "{}:", self.line)?;
+        }
+        write!(f, "{}: {}", self.column, self.message)?;
+        if self.snippet.is_empty() {
+            write!(f, ", found end of line")
+        } else {
+            write!(f, ", found {:?}", self.snippet)
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// 1-based position of `at` in `line`, in characters.
+fn column(line: &str, at: &str) -> usize {
+    let offset = (at.as_ptr() as usize).wrapping_sub(line.as_ptr() as usize);
+    line.get(..offset).map_or(1, |before| before.chars().count() + 1)
+}
+
+/// Parses every line with `f`, attaching line numbers to its errors.
+pub fn lines<T, F>(data: &str, mut f: F) -> Result<Vec<T>, ParseError>
+where
+    F: FnMut(&str) -> Result<T, ParseError>,
+{
+    data.lines()
+        .enumerate()
+        .map(|(idx, line)| f(line).map_err(|e| e.line(idx + 1)))
+        .collect()
+}
+
+/// Parses `at`, a slice of `line`, as a number.
+pub fn number<T>(line: &str, at: &str) -> Result<T, ParseError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    at.parse().map_err(|e| ParseError::at(line, at, format!("invalid number: {}", e)))
+}
+
+/// Strips `prefix` from `at`, a slice of `line`.
+pub fn prefix<'a>(line: &str, at: &'a str, prefix: &str) -> Result<&'a str, ParseError> {
+    at.strip_prefix(prefix)
+        .ok_or_else(|| ParseError::at(line, at, format!("expected '{}'", prefix)))
+}
+
+/// Splits `at`, a slice of `line`, around the first `sep`.
+pub fn split<'a>(line: &str, at: &'a str, sep: &str) -> Result<(&'a str, &'a str), ParseError> {
+    at.split_once(sep)
+        .ok_or_else(|| ParseError::at(line, at, format!("expected '{}'", sep)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{lines, number, split, ParseError};
+
+    #[test]
+    fn location() {
+        let line = "3 ore or 2 clay";
+        let (_, rest) = split(line, line, " ").expect("valid input");
+        let err = split(line, rest, " and ").expect_err("missing separator");
+        assert_eq!(err.column, 3);
+        assert_eq!(err.to_string(), r#"3: expected ' and ', found "ore or 2 clay""#);
+        assert_eq!(err.line(3).day(19).to_string(),
+            r#"day19:3:3: expected ' and ', found "ore or 2 clay""#);
+
+        let part = &line[2..];
+        let err = ParseError::at(part, &part[4..], "oops").within(line, part);
+        assert_eq!(err.column, 7);
+        assert_eq!(ParseError::at_end("ab", "more").to_string(), "3: more, found end of line");
+        assert_eq!(ParseError::at("ab", "elsewhere", "x").column, 1);
+    }
+
+    #[test]
+    fn numbered_lines() {
+        let data = "1\n2\nx3\n";
+        let err = lines(data, |line| number::<u8>(line, line)).expect_err("invalid number");
+        assert_eq!((err.line, err.column), (3, 1));
+        assert_eq!(lines("1\n2", |line| number::<u8>(line, line)).expect("valid input"), [1, 2]);
+    }
+}
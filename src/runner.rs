@@ -0,0 +1,310 @@
+//! Timed execution of registered days and a plain-text summary of results.
+//!
+use std::any::Any;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow as err;
+use rayon::prelude::*;
+
+use crate::expected::{Expected, Status};
+use crate::json::Object;
+use crate::solution::{Answer, Day, Part};
+
+/// Result of a single part run.
+#[derive(Debug)]
+pub struct Outcome {
+    pub day: usize,
+    pub part: Part,
+    pub answer: anyhow::Result<Answer>,
+    pub elapsed: Duration,
+}
+
+/// Single part of a day to run against the given input.
+pub struct Job<'a> {
+    pub day: &'a Day,
+    pub part: Part,
+    pub input: &'a anyhow::Result<String>,
+}
+
+impl Outcome {
+    pub fn is_ok(&self) -> bool {
+        self.answer.is_ok()
+    }
+}
+
+/// Parses a day selection: `all`, a single day `5`, a range `1-10` or a comma-separated
+/// list of those, e.g. `1-3,7`.
+pub fn select_days(spec: &str) -> anyhow::Result<Vec<&'static Day>> {
+    if spec == "all" {
+        return Ok(crate::DAYS.iter().collect());
+    }
+    let mut days = Vec::new();
+    for item in spec.split(',') {
+        let parse = |s: &str| {
+            s.trim().parse::<usize>().map_err(|e| err!("invalid day {:?}: {}", s, e))
+        };
+        let (start, end) = match item.split_once('-') {
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            None => (parse(item)?, parse(item)?),
+        };
+        if start > end {
+            return Err(err!("invalid day range {:?}", item));
+        }
+        for number in start..=end {
+            let day = crate::day(number)
+                .ok_or_else(|| err!("day {} is not implemented", number))?;
+            if !days.iter().any(|d: &&Day| d.number == number) {
+                days.push(day);
+            }
+        }
+    }
+    Ok(days)
+}
+
+/// Runs one part, timing it and turning panics into errors.
+pub fn run(day: &Day, data: &str, part: Part) -> Outcome {
+    let t0 = Instant::now();
+    let answer = panic::catch_unwind(AssertUnwindSafe(|| day.run(data, part)))
+        .unwrap_or_else(|e| Err(err!("panicked: {}", panic_message(e.as_ref()))));
+    Outcome { day: day.number, part, answer, elapsed: t0.elapsed() }
+}
+
+/// Runs every job, optionally in parallel; results keep the order of `jobs`.
+pub fn run_all(jobs: &[Job], parallel: bool) -> Vec<Outcome> {
+    let run_job = |job: &Job| match job.input {
+        Ok(data) => run(job.day, data, job.part),
+        Err(e) => Outcome {
+            day: job.day.number,
+            part: job.part,
+            answer: Err(err!("{:#}", e)),
+            elapsed: Duration::ZERO,
+        },
+    };
+    if parallel {
+        jobs.par_iter().map(run_job).collect()
+    } else {
+        jobs.iter().map(run_job).collect()
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s
+    } else {
+        "unknown panic payload"
+    }
+}
+
+/// Formats duration with a unit suited to its magnitude, e.g. `12.345 ms`.
+pub fn format_duration(d: Duration) -> String {
+    let nanos = d.as_nanos() as f64;
+    if nanos < 1e3 {
+        format!("{} ns", nanos)
+    } else if nanos < 1e6 {
+        format!("{:.3} µs", nanos / 1e3)
+    } else if nanos < 1e9 {
+        format!("{:.3} ms", nanos / 1e6)
+    } else {
+        format!("{:.3} s", nanos / 1e9)
+    }
+}
+
+/// Summary table of outcomes: day, part, answer and wall-clock time,
+/// plus verification status when expected answers are known.
+pub struct Table<'a> {
+    outcomes: &'a [Outcome],
+    expected: Option<&'a Expected>,
+}
+
+impl<'a> Table<'a> {
+    pub fn new(outcomes: &'a [Outcome]) -> Self {
+        Table { outcomes, expected: None }
+    }
+
+    pub fn with_expected(mut self, expected: &'a Expected) -> Self {
+        self.expected = Some(expected);
+        self
+    }
+
+    fn rows(&self) -> Vec<[String; 5]> {
+        let mut rows = Vec::new();
+        for outcome in self.outcomes {
+            let answer = match &outcome.answer {
+                Ok(answer) => answer.value.clone(),
+                Err(e) => format!("error: {:#}", e),
+            };
+            let mut lines = answer.lines();
+            let status = self.expected
+                .map(|e| e.check(outcome).to_string())
+                .unwrap_or_default();
+            rows.push([
+                outcome.day.to_string(),
+                outcome.part.to_string(),
+                lines.next().unwrap_or_default().to_string(),
+                format_duration(outcome.elapsed),
+                status,
+            ]);
+            for line in lines {
+                rows.push([String::new(), String::new(), line.to_string(), String::new(), String::new()]);
+            }
+        }
+        rows
+    }
+}
+
+impl<'a> fmt::Display for Table<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = if self.expected.is_some() { "Status" } else { "" };
+        let header = ["Day", "Part", "Answer", "Time", status].map(String::from);
+        let rows = self.rows();
+        let mut widths = header.clone().map(|h| h.chars().count());
+        for row in &rows {
+            for (w, cell) in widths.iter_mut().zip(row) {
+                *w = (*w).max(cell.chars().count());
+            }
+        }
+        let [d, p, a, t, s] = widths;
+        let line = |f: &mut fmt::Formatter, row: &[String; 5]| {
+            let text = format!("{:>d$}  {:>p$}  {:<a$}  {:>t$}  {:<s$}",
+                row[0], row[1], row[2], row[3], row[4]);
+            writeln!(f, "{}", text.trim_end())
+        };
+        line(f, &header)?;
+        writeln!(f, "{}", "-".repeat(d + p + a + t + 6 + if s > 0 { s + 2 } else { 0 }))?;
+        for row in &rows {
+            line(f, row)?;
+        }
+        let total: Duration = self.outcomes.iter().map(|o| o.elapsed).sum();
+        let failed = self.outcomes.iter().filter(|o| !o.is_ok()).count();
+        write!(f, "{} parts, {} failed", self.outcomes.len(), failed)?;
+        if let Some(expected) = self.expected {
+            let wrong = self.outcomes
+                .iter()
+                .filter(|o| expected.check(o) == Status::Wrong)
+                .count();
+            write!(f, ", {} wrong", wrong)?;
+        }
+        write!(f, ", total time {}", format_duration(total))
+    }
+}
+
+/// Outcomes as JSON Lines: one `{day, part, answer, elapsed_ns, status}` object per line.
+///
+/// Status is `OK`, `WRONG` or `UNKNOWN` as reported by the expected answers, or `ERROR`
+/// for failed runs, whose message is in an additional `error` field.
+pub struct Records<'a> {
+    outcomes: &'a [Outcome],
+    expected: Option<&'a Expected>,
+}
+
+impl<'a> Records<'a> {
+    pub fn new(outcomes: &'a [Outcome]) -> Self {
+        Records { outcomes, expected: None }
+    }
+
+    pub fn with_expected(mut self, expected: &'a Expected) -> Self {
+        self.expected = Some(expected);
+        self
+    }
+
+    fn record(&self, outcome: &Outcome) -> String {
+        let mut out = String::new();
+        let obj = Object::new(&mut out)
+            .field("day", &outcome.day)
+            .field("part", &outcome.part.number());
+        let obj = match &outcome.answer {
+            Ok(answer) => {
+                let status = self.expected.map_or(Status::Unknown, |e| e.check(outcome));
+                obj.field("answer", &answer.value)
+                    .field("elapsed_ns", &(outcome.elapsed.as_nanos() as u64))
+                    .field("status", &status.to_string())
+            }
+            Err(e) => obj.field("answer", &None::<String>)
+                .field("elapsed_ns", &(outcome.elapsed.as_nanos() as u64))
+                .field("status", "ERROR")
+                .field("error", &format!("{:#}", e)),
+        };
+        obj.finish();
+        out
+    }
+}
+
+impl<'a> fmt::Display for Records<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (idx, outcome) in self.outcomes.iter().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", self.record(outcome))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format_duration, run, select_days, Records, Table};
+    use crate::solution::Part;
+    use std::time::Duration;
+
+    #[test]
+    fn failures() {
+        let day = crate::day(1).expect("day 1 is registered");
+        let ok = run(day, "1\n2\n\n4\n", Part::One);
+        assert_eq!(ok.answer.expect("valid input").value, "4");
+        let bad = run(day, "x\n", Part::One);
+        assert!(bad.answer.is_err());
+    }
+
+    #[test]
+    fn table() {
+        let day = crate::day(1).expect("day 1 is registered");
+        let outcomes = [run(day, "1\n\n2\n", Part::One), run(day, "a", Part::Two)];
+        let table = Table::new(&outcomes).to_string();
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[2].starts_with("  1     1  2 "));
+        assert!(lines[3].contains("error: "));
+        assert!(lines[4].starts_with("2 parts, 1 failed"));
+    }
+
+    #[test]
+    fn records() {
+        let day1 = crate::day(1).expect("day 1 is registered");
+        let day10 = crate::day(10).expect("day 10 is registered");
+        let outcomes = [run(day10, &"noop\n".repeat(240), Part::Two), run(day1, "x", Part::One)];
+        let records = Records::new(&outcomes).to_string();
+        let lines: Vec<_> = records.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let row = "###".to_string() + &".".repeat(37);
+        let image = format!("{}\\n", row).repeat(6);
+        assert!(lines[0].starts_with(&format!(r#"{{"day":10,"part":2,"answer":"{}","#, image)));
+        assert!(lines[0].ends_with(r#""status":"UNKNOWN"}"#));
+        assert!(lines[1].starts_with(r#"{"day":1,"part":1,"answer":null,"elapsed_ns":"#));
+        assert!(lines[1].contains(r#""status":"ERROR","error":""#));
+    }
+
+    #[test]
+    fn selection() {
+        let numbers = |spec| -> Vec<usize> {
+            select_days(spec).expect("valid selection").iter().map(|d| d.number).collect()
+        };
+        assert_eq!(numbers("5"), vec![5]);
+        assert_eq!(numbers("1-3,7,2"), vec![1, 2, 3, 7]);
+        assert_eq!(numbers("all").len(), crate::DAYS.len());
+        assert!(select_days("3-1").is_err());
+        assert!(select_days("99").is_err());
+        assert!(select_days("x").is_err());
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(format_duration(Duration::from_nanos(999)), "999 ns");
+        assert_eq!(format_duration(Duration::from_micros(1500)), "1.500 ms");
+        assert_eq!(format_duration(Duration::from_secs(2)), "2.000 s");
+    }
+}
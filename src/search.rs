@@ -0,0 +1,258 @@
+//! Graph searches over nodes yielded by a `neighbours` closure.
+//!
+//! Nodes are owned values, the closures may build them on the fly.
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::hash::Hash;
+use std::ops::Add;
+
+/// Breadth-first search of the shortest path from `start` to a node satisfying `is_goal`.
+pub fn bfs<T, G, F, I>(start: T, is_goal: G, neighbours: F) -> Option<Vec<T>>
+where
+    T: Clone + Eq + Hash,
+    G: FnMut(&T) -> bool,
+    F: FnMut(&T) -> I,
+    I: IntoIterator<Item=T>,
+{
+    multi_bfs([start], is_goal, neighbours)
+}
+
+/// Breadth-first search of the shortest path from any of `starts` to a node satisfying `is_goal`.
+pub fn multi_bfs<T, S, G, F, I>(starts: S, mut is_goal: G, neighbours: F) -> Option<Vec<T>>
+where
+    T: Clone + Eq + Hash,
+    S: IntoIterator<Item=T>,
+    G: FnMut(&T) -> bool,
+    F: FnMut(&T) -> I,
+    I: IntoIterator<Item=T>,
+{
+    let (tree, goal) = breadth_first(starts, &mut is_goal, neighbours);
+    goal.map(|id| tree.path(id))
+}
+
+/// Number of steps from the closest of `starts` to every node reachable from them.
+pub fn bfs_distances<T, S, F, I>(starts: S, neighbours: F) -> HashMap<T, usize>
+where
+    T: Clone + Eq + Hash,
+    S: IntoIterator<Item=T>,
+    F: FnMut(&T) -> I,
+    I: IntoIterator<Item=T>,
+{
+    breadth_first(starts, |_| false, neighbours).0.into_distances()
+}
+
+/// Cheapest path from `start` to a node satisfying `is_goal`, and its cost.
+/// `neighbours` yields the next nodes with the cost of moving there.
+pub fn dijkstra<T, C, G, F, I>(start: T, is_goal: G, neighbours: F) -> Option<(Vec<T>, C)>
+where
+    T: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output=C>,
+    G: FnMut(&T) -> bool,
+    F: FnMut(&T) -> I,
+    I: IntoIterator<Item=(T, C)>,
+{
+    a_star(start, is_goal, neighbours, |_| C::default())
+}
+
+/// Cost from the closest of `starts` to every node reachable from them.
+pub fn dijkstra_distances<T, C, S, F, I>(starts: S, neighbours: F) -> HashMap<T, C>
+where
+    T: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output=C>,
+    S: IntoIterator<Item=T>,
+    F: FnMut(&T) -> I,
+    I: IntoIterator<Item=(T, C)>,
+{
+    best_first(starts, |_| false, neighbours, |_| C::default()).0.into_distances()
+}
+
+/// Like `dijkstra`, exploring first the nodes with the lowest cost plus `heuristic`.
+/// The path is the cheapest as long as `heuristic` never overestimates the cost left to a goal.
+pub fn a_star<T, C, G, F, I, H>(start: T, is_goal: G, neighbours: F, heuristic: H) -> Option<(Vec<T>, C)>
+where
+    T: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output=C>,
+    G: FnMut(&T) -> bool,
+    F: FnMut(&T) -> I,
+    I: IntoIterator<Item=(T, C)>,
+    H: FnMut(&T) -> C,
+{
+    let (tree, goal) = best_first([start], is_goal, neighbours, heuristic);
+    goal.map(|id| (tree.path(id), tree.costs[id]))
+}
+
+/// Nodes reached by a search, with their best known cost and the node they were reached from.
+struct Tree<T, C> {
+    nodes: Vec<T>,
+    ids: HashMap<T, usize>,
+    parents: Vec<Option<usize>>,
+    costs: Vec<C>,
+}
+
+impl<T: Clone + Eq + Hash, C: Copy + Ord> Tree<T, C> {
+    fn new() -> Self {
+        Tree { nodes: Vec::new(), ids: HashMap::new(), parents: Vec::new(), costs: Vec::new() }
+    }
+
+    /// Records that `node` is reached from `parent` at `cost`,
+    /// returns its id unless it was already reached at no higher cost.
+    fn reach(&mut self, node: T, parent: Option<usize>, cost: C) -> Option<usize> {
+        match self.ids.get(&node) {
+            Some(&id) if self.costs[id] <= cost => None,
+            Some(&id) => {
+                self.parents[id] = parent;
+                self.costs[id] = cost;
+                Some(id)
+            }
+            None => {
+                let id = self.nodes.len();
+                self.ids.insert(node.clone(), id);
+                self.nodes.push(node);
+                self.parents.push(parent);
+                self.costs.push(cost);
+                Some(id)
+            }
+        }
+    }
+
+    /// Nodes from a start to the node `id`.
+    fn path(&self, id: usize) -> Vec<T> {
+        let mut path = vec![self.nodes[id].clone()];
+        let mut id = id;
+        while let Some(parent) = self.parents[id] {
+            path.push(self.nodes[parent].clone());
+            id = parent;
+        }
+        path.reverse();
+        path
+    }
+
+    fn into_distances(self) -> HashMap<T, C> {
+        self.nodes.into_iter().zip(self.costs).collect()
+    }
+}
+
+/// Explores the nodes in order of steps from `starts`, until one satisfies `is_goal`.
+fn breadth_first<T, S, G, F, I>(starts: S, mut is_goal: G, mut neighbours: F) -> (Tree<T, usize>, Option<usize>)
+where
+    T: Clone + Eq + Hash,
+    S: IntoIterator<Item=T>,
+    G: FnMut(&T) -> bool,
+    F: FnMut(&T) -> I,
+    I: IntoIterator<Item=T>,
+{
+    let mut tree = Tree::new();
+    let mut queue: VecDeque<_> = starts.into_iter().filter_map(|start| tree.reach(start, None, 0)).collect();
+    while let Some(id) = queue.pop_front() {
+        if is_goal(&tree.nodes[id]) {
+            return (tree, Some(id))
+        }
+        let steps = tree.costs[id] + 1;
+        for next in neighbours(&tree.nodes[id]) {
+            queue.extend(tree.reach(next, Some(id), steps));
+        }
+    }
+    (tree, None)
+}
+
+/// Explores the nodes in order of cost from `starts` plus `heuristic`, until one satisfies `is_goal`.
+fn best_first<T, C, S, G, F, I, H>(
+    starts: S,
+    mut is_goal: G,
+    mut neighbours: F,
+    mut heuristic: H,
+) -> (Tree<T, C>, Option<usize>)
+where
+    T: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output=C>,
+    S: IntoIterator<Item=T>,
+    G: FnMut(&T) -> bool,
+    F: FnMut(&T) -> I,
+    I: IntoIterator<Item=(T, C)>,
+    H: FnMut(&T) -> C,
+{
+    let mut tree = Tree::new();
+    let mut todo = BinaryHeap::new();
+    for start in starts {
+        let estimate = heuristic(&start);
+        if let Some(id) = tree.reach(start, None, C::default()) {
+            todo.push(Reverse((estimate, C::default(), id)));
+        }
+    }
+    while let Some(Reverse((_, cost, id))) = todo.pop() {
+        if cost > tree.costs[id] {
+            // reached again at a lower cost since
+            continue
+        }
+        if is_goal(&tree.nodes[id]) {
+            return (tree, Some(id))
+        }
+        for (next, step) in neighbours(&tree.nodes[id]) {
+            let cost = cost + step;
+            let estimate = cost + heuristic(&next);
+            if let Some(next) = tree.reach(next, Some(id), cost) {
+                todo.push(Reverse((estimate, cost, next)));
+            }
+        }
+    }
+    (tree, None)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{a_star, bfs, bfs_distances, dijkstra, dijkstra_distances, multi_bfs};
+    use crate::fuzz::Rng;
+
+    /// Moves on a line of integers, by one or by doubling.
+    fn moves(&n: &i64) -> Vec<i64> {
+        vec![n - 1, n + 1, n * 2]
+    }
+
+    #[test]
+    fn breadth_first() {
+        assert_eq!(bfs(3, |&n| n == 21, moves), Some(vec![3, 4, 5, 10, 20, 21]));
+        assert_eq!(bfs(3, |&n| n == 3, moves), Some(vec![3]));
+        assert_eq!(bfs(3, |_| false, |&n| (n < 10).then_some(n + 1)), None);
+        assert_eq!(multi_bfs([1, 30], |&n| n == 29, moves), Some(vec![30, 29]));
+
+        let distances = bfs_distances([0, 10], |&n: &i64| [n - 1, n + 1].into_iter().filter(|n| n.abs() <= 20));
+        assert_eq!(distances.len(), 41);
+        assert_eq!(distances[&5], 5);
+        assert_eq!(distances[&6], 4);
+        assert_eq!(distances[&-20], 20);
+    }
+
+    #[test]
+    fn weighted() {
+        // doubling is expensive
+        let weighted = |&n: &i64| [(n - 1, 1), (n + 1, 1), (n * 2, 5)];
+        assert_eq!(dijkstra(3, |&n| n == 21, weighted), Some((vec![3, 4, 5, 10, 20, 21], 13)));
+        assert_eq!(dijkstra(3, |&n| n == 9, weighted), Some(((3..=9).collect(), 6)));
+        assert_eq!(dijkstra(3, |&n| n == 40, weighted), Some((vec![3, 4, 5, 10, 20, 40], 17)));
+        let distances = dijkstra_distances([0], |&n: &i64| [(n + 1, 2), (n + 3, 5)].into_iter().filter(|(n, _)| *n <= 6));
+        assert_eq!(distances[&6], 10);
+        assert_eq!(distances[&3], 5);
+    }
+
+    #[test]
+    fn same_costs() {
+        // random weighted grids, where A* with the distance left must find the costs of Dijkstra
+        let mut rng = Rng::new(5);
+        for _ in 0..50 {
+            let size = 2 + rng.below(10) as i32;
+            let weights: Vec<u32> = (0..size * size).map(|_| 1 + rng.below(9) as u32).collect();
+            let goal = (size - 1, size - 1);
+            let neighbours = |&(x, y): &(i32, i32)| {
+                [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+                    .into_iter()
+                    .filter(|&(x, y)| x >= 0 && x < size && y >= 0 && y < size)
+                    .map(|(x, y)| ((x, y), weights[(y * size + x) as usize]))
+            };
+            let distance = |&(x, y): &(i32, i32)| x.abs_diff(goal.0) + y.abs_diff(goal.1);
+            let (path, cost) = a_star((0, 0), |&p| p == goal, neighbours, distance).expect("connected grid");
+            assert_eq!(Some(cost), dijkstra((0, 0), |&p| p == goal, neighbours).map(|(_, cost)| cost));
+            assert_eq!(Some(&cost), dijkstra_distances([(0, 0)], neighbours).get(&goal));
+            assert_eq!(path.iter().skip(1).map(|&(x, y)| weights[(y * size + x) as usize]).sum::<u32>(), cost);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Common interface implemented by every day's puzzle.
+//!
+use std::fmt;
+use std::str;
+
+use anyhow::anyhow as err;
+
+use crate::graph::Renderer;
+use crate::parser::ParseError;
+
+/// A puzzle solution: input parsing plus two parts answered from the parsed input.
+pub trait Solution {
+    type Input;
+
+    fn parse(data: &str) -> anyhow::Result<Self::Input>;
+    fn part1(input: &Self::Input) -> anyhow::Result<Answer>;
+    fn part2(input: &Self::Input) -> anyhow::Result<Answer>;
+
+    /// Optional animation of the solving process, drawn with `out`.
+    fn visualize(_input: &Self::Input, _out: &mut dyn Renderer) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Puzzle part selector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Part {
+    One,
+    Two,
+}
+
+impl Part {
+    pub const BOTH: [Part; 2] = [Part::One, Part::Two];
+
+    pub fn number(self) -> usize {
+        match self {
+            Part::One => 1,
+            Part::Two => 2,
+        }
+    }
+}
+
+impl str::FromStr for Part {
+    type Err = anyhow::Error;
+    fn from_str(val: &str) -> anyhow::Result<Self> {
+        match val {
+            "1" => Ok(Part::One),
+            "2" => Ok(Part::Two),
+            x => Err(err!("invalid part {:?}, expected 1 or 2", x)),
+        }
+    }
+}
+
+impl fmt::Display for Part {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Part::One => write!(f, "1"),
+            Part::Two => write!(f, "2"),
+        }
+    }
+}
+
+/// Puzzle answer together with a short human-readable description.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Answer {
+    pub label: &'static str,
+    pub value: String,
+}
+
+impl Answer {
+    pub fn new<T: ToString>(label: &'static str, value: T) -> Self {
+        Answer { label, value: value.to_string() }
+    }
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.value.contains('\n') {
+            write!(f, "{}:\n{}", self.label, self.value.trim_end())
+        } else {
+            write!(f, "{}: {}", self.label, self.value)
+        }
+    }
+}
+
+/// Registry entry: a day number bound to its type-erased [`Solution`].
+pub struct Day {
+    pub number: usize,
+    run: fn(&str, Part) -> anyhow::Result<Answer>,
+    visualize: fn(&str, &mut dyn Renderer) -> anyhow::Result<()>,
+}
+
+impl Day {
+    pub const fn new<S: Solution>(number: usize) -> Self {
+        Day { number, run: run::<S>, visualize: visualize::<S> }
+    }
+
+    /// Parses the input and computes the answer for a single part.
+    pub fn run(&self, data: &str, part: Part) -> anyhow::Result<Answer> {
+        (self.run)(data, part).map_err(|e| self.locate(e))
+    }
+
+    pub fn visualize(&self, data: &str, out: &mut dyn Renderer) -> anyhow::Result<()> {
+        (self.visualize)(data, out).map_err(|e| self.locate(e))
+    }
+
+    /// Tags parse errors with the day number.
+    fn locate(&self, e: anyhow::Error) -> anyhow::Error {
+        match e.downcast::<ParseError>() {
+            Ok(e) => e.day(self.number).into(),
+            Err(e) => e,
+        }
+    }
+}
+
+fn run<S: Solution>(data: &str, part: Part) -> anyhow::Result<Answer> {
+    let input = S::parse(data)?;
+    match part {
+        Part::One => S::part1(&input),
+        Part::Two => S::part2(&input),
+    }
+}
+
+fn visualize<S: Solution>(data: &str, out: &mut dyn Renderer) -> anyhow::Result<()> {
+    S::visualize(&S::parse(data)?, out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Answer, Part};
+    use crate::parser::ParseError;
+    use crate::DAYS;
+
+    #[test]
+    fn registry() {
+        let numbers: Vec<_> = DAYS.iter().map(|d| d.number).collect();
+        assert_eq!(numbers, (1..=DAYS.len()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Answer::new("Max", 42).to_string(), "Max: 42");
+        assert_eq!(Answer::new("Image", "#.\n.#\n").to_string(), "Image:\n#.\n.#");
+    }
+
+    #[test]
+    fn parse_errors() {
+        let day = crate::day(4).expect("day 4 is registered");
+        let err = day.run("2-4,6-8\n2-3;4-5\n", Part::One).expect_err("invalid input");
+        let err = err.downcast::<ParseError>().expect("parse error");
+        assert_eq!((err.day, err.line, err.column), (Some(4), 2, 1));
+    }
+
+    #[test]
+    fn part() {
+        assert_eq!("1".parse::<Part>().expect("valid part"), Part::One);
+        assert_eq!("2".parse::<Part>().expect("valid part"), Part::Two);
+        assert!("3".parse::<Part>().is_err());
+    }
+}